@@ -1,4 +1,4 @@
-import { render } from "../../wasm/pkg/gs";
+import { render, RenderSettings, WebRenderer } from "../../wasm/pkg/gs";
 import { loadFile } from "./ply";
 import preprocessShader from "../../wasm/src/shader/preprocess.wgsl?raw";
 import {
@@ -7,6 +7,10 @@ import {
   makeStructuredView,
 } from "webgpu-utils";
 import { loadCamera, preprocess } from "./util";
+import { enterXr, isXrSupported } from "./xr";
+
+// size of every eye in immersive sessions
+const XR_VIEW_SIZE = 1440;
 
 const file = document.getElementsByTagName("input")[0];
 
@@ -31,12 +35,35 @@ file.addEventListener("change", async (e) => {
 
   const cameras = loadCamera();
 
+  // mip-splatting models carry a per-gaussian 3d filter
+  const settings =
+    "filter_3D" in gaussians[0]
+      ? RenderSettings.mip_splatting()
+      : new RenderSettings();
+
   // for (const camera of cameras) {
   render(
     new Float32Array(gaussianStructure.arrayBuffer),
     BigInt(gaussians.length),
     new Float32Array(cameras[0].cameraParam),
-    new Uint32Array(cameras[0].sizeParam)
+    new Uint32Array(cameras[0].sizeParam),
+    settings
   );
   // }
+
+  if (await isXrSupported()) {
+    const xrCanvas = document.createElement("canvas");
+    const xrRenderer = await WebRenderer.create(
+      xrCanvas,
+      new Float32Array(gaussianStructure.arrayBuffer),
+      BigInt(gaussians.length),
+      XR_VIEW_SIZE,
+      XR_VIEW_SIZE,
+      settings
+    );
+
+    const button = document.getElementById("xr")!;
+    button.hidden = false;
+    button.onclick = () => enterXr(xrRenderer, xrCanvas);
+  }
 });
@@ -63,6 +63,8 @@ const properties = [
   "rot_1",
   "rot_2",
   "rot_3",
+  // mip-splatting only
+  "filter_3D",
 ] as const;
 type Property = (typeof properties)[number];
 
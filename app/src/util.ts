@@ -16,6 +16,7 @@ export const preprocess = (gaussian: Gaussian) => {
   return {
     mean: [gaussian.x, gaussian.y, gaussian.z],
     norm: [gaussian.nx, gaussian.ny, gaussian.nz],
+    filter_3d: gaussian.filter_3D ?? 0,
     sh: [
       [gaussian.f_dc_0, gaussian.f_dc_1, gaussian.f_dc_2],
       [gaussian.f_rest_0, gaussian.f_rest_1, gaussian.f_rest_2],
@@ -45,8 +46,20 @@ export const preprocess = (gaussian: Gaussian) => {
   };
 };
 
+/** Entry of a `cameras.json`, the principal point is the image center when missing. */
+interface CameraJson {
+  width: number;
+  height: number;
+  position: number[];
+  rotation: number[][];
+  fx: number;
+  fy: number;
+  cx?: number;
+  cy?: number;
+}
+
 export const loadCamera = () => {
-  return cameraJson.map((json) => {
+  return (cameraJson as CameraJson[]).map((json) => {
     const obj = new Object3D();
 
     obj.translateX(json.position[0]);
@@ -94,6 +107,8 @@ export const loadCamera = () => {
         ...obj.up.multiplyScalar(-1).toArray(),
         json.fx,
         json.fy,
+        json.cx ?? json.width / 2,
+        json.cy ?? json.height / 2,
       ],
       sizeParam: [json.width, json.height],
     };
@@ -0,0 +1,140 @@
+import { WebRenderer } from "../../wasm/pkg/gs";
+
+// the subset of WebXR used here, not part of the dom typings yet
+declare global {
+  interface Navigator {
+    xr?: {
+      isSessionSupported(mode: string): Promise<boolean>;
+      requestSession(mode: string): Promise<XRSession>;
+    };
+  }
+
+  interface XRSession extends EventTarget {
+    requestReferenceSpace(type: string): Promise<XRReferenceSpace>;
+    requestAnimationFrame(
+      callback: (time: number, frame: XRFrame) => void
+    ): number;
+    updateRenderState(state: { baseLayer: XRWebGLLayer }): void;
+  }
+
+  type XRReferenceSpace = EventTarget;
+
+  interface XRFrame {
+    getViewerPose(space: XRReferenceSpace): { views: XRView[] } | undefined;
+  }
+
+  type XRView = object;
+
+  interface XRWebGLLayer {
+    readonly framebuffer: WebGLFramebuffer | null;
+    getViewport(view: XRView): {
+      x: number;
+      y: number;
+      width: number;
+      height: number;
+    };
+  }
+
+  // eslint-disable-next-line no-var
+  var XRWebGLLayer: {
+    new (session: XRSession, gl: WebGL2RenderingContext): XRWebGLLayer;
+  };
+}
+
+export const isXrSupported = async () =>
+  (await navigator.xr?.isSessionSupported("immersive-vr")) ?? false;
+
+const vertexShader = `#version 300 es
+out vec2 uv;
+void main() {
+  // fullscreen triangle
+  vec2 position = vec2(gl_VertexID & 1, gl_VertexID >> 1) * 4.0 - 1.0;
+  uv = position * 0.5 + 0.5;
+  gl_Position = vec4(position, 0.0, 1.0);
+}`;
+
+const fragmentShader = `#version 300 es
+precision highp float;
+uniform sampler2D image;
+// views side by side in the image
+uniform float view;
+uniform float numView;
+in vec2 uv;
+out vec4 color;
+void main() {
+  color = texture(image, vec2((view + uv.x) / numView, 1.0 - uv.y));
+}`;
+
+const createProgram = (gl: WebGL2RenderingContext) => {
+  const program = gl.createProgram()!;
+
+  for (const [type, source] of [
+    [gl.VERTEX_SHADER, vertexShader],
+    [gl.FRAGMENT_SHADER, fragmentShader],
+  ] as const) {
+    const shader = gl.createShader(type)!;
+    gl.shaderSource(shader, source);
+    gl.compileShader(shader);
+    gl.attachShader(program, shader);
+  }
+
+  gl.linkProgram(program);
+  return program;
+};
+
+/**
+ * Starts an immersive session rendering the scene of `renderer`. The views are drawn by
+ * WebGPU side by side into `canvas` and copied into the WebGL framebuffer of the session.
+ */
+export const enterXr = async (
+  renderer: WebRenderer,
+  canvas: HTMLCanvasElement
+) => {
+  const session = await navigator.xr!.requestSession("immersive-vr");
+
+  const gl = document
+    .createElement("canvas")
+    .getContext("webgl2", { xrCompatible: true })!;
+  const layer = new XRWebGLLayer(session, gl);
+  session.updateRenderState({ baseLayer: layer });
+
+  const space = await session.requestReferenceSpace("local");
+
+  const program = createProgram(gl);
+  const texture = gl.createTexture();
+  gl.bindTexture(gl.TEXTURE_2D, texture);
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+
+  const onFrame = (_time: number, frame: XRFrame) => {
+    session.requestAnimationFrame(onFrame);
+
+    const pose = frame.getViewerPose(space);
+    if (!pose || !renderer.render_xr_frame(frame, space)) return;
+
+    gl.bindFramebuffer(gl.FRAMEBUFFER, layer.framebuffer);
+    gl.useProgram(program);
+    gl.bindTexture(gl.TEXTURE_2D, texture);
+    gl.texImage2D(
+      gl.TEXTURE_2D,
+      0,
+      gl.RGBA,
+      gl.RGBA,
+      gl.UNSIGNED_BYTE,
+      canvas
+    );
+    // the renderer draws the first views of the pose into the slots of the canvas
+    const numSlot = canvas.width / renderer.view_width();
+    gl.uniform1f(gl.getUniformLocation(program, "numView"), numSlot);
+
+    pose.views.slice(0, numSlot).forEach((view, i) => {
+      const viewport = layer.getViewport(view);
+      gl.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+      gl.uniform1f(gl.getUniformLocation(program, "view"), i);
+      gl.drawArrays(gl.TRIANGLES, 0, 3);
+    });
+  };
+
+  session.requestAnimationFrame(onFrame);
+};
@@ -0,0 +1,288 @@
+use nalgebra::{Matrix3, Matrix4, Point3, Unit, Vector2, Vector3, Vector4};
+use serde::Deserialize;
+
+/// How view space is mapped onto the image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraModel {
+    /// Perspective projection through the focal length and principal point.
+    Pinhole,
+    /// OpenCV fisheye, the angle to the optical axis distorted by
+    /// `theta * (1 + k1 theta^2 + k2 theta^4 + k3 theta^6 + k4 theta^8)` is proportional to the
+    /// distance to the principal point.
+    Fisheye { distortion: [f32; 4] },
+    /// 360° panorama, longitude along the image width and latitude along its height with
+    /// view -y at the top. Focal length and principal point are ignored.
+    Equirectangular,
+    /// Parallel projection, the focal length is in pixels per world unit and a view space
+    /// point lands on `focal * xy + principal`.
+    Orthographic,
+}
+
+impl CameraModel {
+    /// `model` of the `Projection` uniform of preprocess.wgsl
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            Self::Pinhole => 0,
+            Self::Fisheye { .. } => 1,
+            Self::Equirectangular => 2,
+            Self::Orthographic => 3,
+        }
+    }
+
+    pub(crate) fn distortion(&self) -> [f32; 4] {
+        match self {
+            Self::Fisheye { distortion } => *distortion,
+            _ => [0.0; 4],
+        }
+    }
+}
+
+/// Camera looking down +z of its view space with y down, as in COLMAP.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// world to view transform
+    pub view: Matrix4<f32>,
+    /// focal length in pixels
+    pub focal: Vector2<f32>,
+    /// principal point in pixels from the top left corner of the image
+    pub principal: Vector2<f32>,
+    pub model: CameraModel,
+}
+
+impl Camera {
+    pub fn new(view: Matrix4<f32>, focal: Vector2<f32>, principal: Vector2<f32>) -> Self {
+        Self {
+            view,
+            focal,
+            principal,
+            model: CameraModel::Pinhole,
+        }
+    }
+
+    pub fn with_model(mut self, model: CameraModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Camera at `position` with the camera to world `rotation`.
+    pub fn from_pose(
+        rotation: &Matrix3<f32>,
+        position: &Point3<f32>,
+        focal: Vector2<f32>,
+        principal: Vector2<f32>,
+    ) -> Self {
+        let rotation = rotation.transpose();
+        let translation = -(rotation * position.coords);
+
+        let mut view = rotation.to_homogeneous();
+        view.fixed_view_mut::<3, 1>(0, 3).copy_from(&translation);
+
+        Self::new(view, focal, principal)
+    }
+
+    /// Camera with the intrinsics of an OpenGL style `projection` for a `width` x `height`
+    /// view, as handed out per eye by XR runtimes, possibly off center.
+    pub fn from_projection(
+        view: Matrix4<f32>,
+        projection: &Matrix4<f32>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let half_size = Vector2::new(width as f32, height as f32) * 0.5;
+        let focal = Vector2::new(projection[(0, 0)], projection[(1, 1)]).component_mul(&half_size);
+        // ndc y points up, pixel y down
+        let principal = Vector2::new(1.0 - projection[(0, 2)], 1.0 + projection[(1, 2)])
+            .component_mul(&half_size);
+
+        Self::new(view, focal, principal)
+    }
+
+    /// Camera of a WebXR view, from its `transform` (view to world, OpenGL axes with y up and
+    /// -z forward) and `projection`.
+    pub fn from_xr_view(
+        transform: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let gl_view = transform
+            .try_inverse()
+            .expect("view transform is not invertible");
+        // y down and z forward
+        let view = Matrix4::from_diagonal(&Vector4::new(1.0, -1.0, -1.0, 1.0)) * gl_view;
+
+        Self::from_projection(view, projection, width, height)
+    }
+
+    /// Left and right eye `ipd` apart, along the x axis of this camera.
+    pub fn stereo(&self, ipd: f32) -> (Self, Self) {
+        let eye = |offset: f32| {
+            let view = Matrix4::new_translation(&Vector3::new(offset, 0.0, 0.0)) * self.view;
+            Self::new(view, self.focal, self.principal).with_model(self.model)
+        };
+
+        // the left eye sees the scene shifted to the right
+        (eye(ipd * 0.5), eye(-ipd * 0.5))
+    }
+
+    /// Camera center in world space.
+    pub fn position(&self) -> Point3<f32> {
+        let rotation = self.view.fixed_view::<3, 3>(0, 0);
+        let translation = self.view.fixed_view::<3, 1>(0, 3);
+
+        Point3::from(-(rotation.transpose() * translation))
+    }
+
+    /// Field of view of an image of `width` x `height` pixels.
+    pub fn fov(&self, width: u32, height: u32) -> Vector2<f32> {
+        Vector2::new(
+            2.0 * f32::atan(width as f32 / (2.0 * self.focal.x)),
+            2.0 * f32::atan(height as f32 / (2.0 * self.focal.y)),
+        )
+    }
+
+    /// View to clip space of a `width` x `height` image, in the view space of the camera and
+    /// with the depth in [0, 1] between `near` and `far`. Ndc x and y grow with pixel x and y,
+    /// a view space point lands on `focal * xy / z + principal`, or `focal * xy + principal`
+    /// for an orthographic camera. The other models can not be expressed as a matrix, they
+    /// get the pinhole projection.
+    pub fn projection(&self, width: u32, height: u32, near: f32, far: f32) -> Matrix4<f32> {
+        let (width, height) = (width as f32, height as f32);
+
+        if self.model == CameraModel::Orthographic {
+            return Matrix4::new(
+                2.0 * self.focal.x / width,
+                0.0,
+                0.0,
+                2.0 * self.principal.x / width - 1.0,
+                0.0,
+                2.0 * self.focal.y / height,
+                0.0,
+                2.0 * self.principal.y / height - 1.0,
+                0.0,
+                0.0,
+                1.0 / (far - near),
+                -near / (far - near),
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            );
+        }
+
+        Matrix4::new(
+            2.0 * self.focal.x / width,
+            0.0,
+            2.0 * self.principal.x / width - 1.0,
+            0.0,
+            0.0,
+            2.0 * self.focal.y / height,
+            2.0 * self.principal.y / height - 1.0,
+            0.0,
+            0.0,
+            0.0,
+            far / (far - near),
+            -far * near / (far - near),
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+        )
+    }
+}
+
+/// Camera of a trained scene, an entry of its `cameras.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DatasetCamera {
+    pub id: u32,
+    pub img_name: String,
+    pub width: u32,
+    pub height: u32,
+    /// camera center in world space
+    pub position: [f32; 3],
+    /// camera to world rotation, row major
+    pub rotation: [[f32; 3]; 3],
+    pub fx: f32,
+    pub fy: f32,
+    /// principal point, the image center when missing
+    #[serde(default)]
+    pub cx: Option<f32>,
+    #[serde(default)]
+    pub cy: Option<f32>,
+    /// OpenCV fisheye coefficients k1 to k4, a pinhole camera when missing
+    #[serde(default)]
+    pub distortion: Option<[f32; 4]>,
+}
+
+impl DatasetCamera {
+    /// Parses the cameras of a `cameras.json`.
+    pub fn load(json: &str) -> serde_json::Result<Vec<Self>> {
+        serde_json::from_str(json)
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera::from_pose(
+            &self.rotation(),
+            &Point3::from(self.position),
+            Vector2::new(self.fx, self.fy),
+            self.principal(),
+        )
+        .with_model(self.model())
+    }
+
+    pub fn model(&self) -> CameraModel {
+        match self.distortion {
+            Some(distortion) => CameraModel::Fisheye { distortion },
+            None => CameraModel::Pinhole,
+        }
+    }
+
+    /// Principal point in pixels.
+    pub fn principal(&self) -> Vector2<f32> {
+        Vector2::new(
+            self.cx.unwrap_or(self.width as f32 * 0.5),
+            self.cy.unwrap_or(self.height as f32 * 0.5),
+        )
+    }
+
+    /// Camera to world rotation.
+    pub fn rotation(&self) -> Matrix3<f32> {
+        Matrix3::from_fn(|i, j| self.rotation[i][j])
+    }
+
+    /// Point closest to the optical axes of all `cameras` in the least squares sense, what
+    /// a capture circling an object looks at. Their mean position if the axes are parallel.
+    pub fn focus(cameras: &[Self]) -> Point3<f32> {
+        let mut lhs = Matrix3::zeros();
+        let mut rhs = Vector3::zeros();
+        for camera in cameras {
+            let axis = camera.rotation().column(2).normalize();
+            // projects onto the plane orthogonal to the axis
+            let projection = Matrix3::identity() - axis * axis.transpose();
+            lhs += projection;
+            rhs += projection * Vector3::from(camera.position);
+        }
+
+        match lhs.try_inverse() {
+            Some(inverse) if cameras.len() > 1 => Point3::from(inverse * rhs),
+            _ => Point3::from(
+                cameras
+                    .iter()
+                    .map(|camera| Vector3::from(camera.position))
+                    .sum::<Vector3<f32>>()
+                    / cameras.len() as f32,
+            ),
+        }
+    }
+
+    /// Mean up direction of the images of `cameras`.
+    pub fn up(cameras: &[Self]) -> Unit<Vector3<f32>> {
+        // image y points down
+        Unit::new_normalize(
+            -cameras
+                .iter()
+                .map(|camera| camera.rotation().column(1).into_owned())
+                .sum::<Vector3<f32>>(),
+        )
+    }
+}
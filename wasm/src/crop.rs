@@ -0,0 +1,136 @@
+use nalgebra::{Matrix4, Point3, UnitQuaternion, Vector3, Vector4};
+
+/// Region in world space, see [`Crop`].
+#[derive(Clone, Debug)]
+pub enum CropShape {
+    /// Axis aligned box between two corners.
+    Box {
+        min: Point3<f32>,
+        max: Point3<f32>,
+    },
+    OrientedBox {
+        center: Point3<f32>,
+        /// along the axes of the box
+        half_extent: Vector3<f32>,
+        /// box to world
+        rotation: UnitQuaternion<f32>,
+    },
+    Sphere {
+        center: Point3<f32>,
+        radius: f32,
+    },
+    /// Half space where `normal · p + distance >= 0`.
+    Plane {
+        normal: Vector3<f32>,
+        distance: f32,
+    },
+}
+
+/// Crop volume, Gaussians with their mean outside of it are discarded before tile binning.
+/// With several volumes a Gaussian has to pass all of them.
+#[derive(Clone, Debug)]
+pub struct Crop {
+    pub shape: CropShape,
+    /// Discards the inside instead, to cut away a floater.
+    pub invert: bool,
+}
+
+impl Crop {
+    pub fn new(shape: CropShape) -> Self {
+        Self {
+            shape,
+            invert: false,
+        }
+    }
+
+    pub fn inverted(shape: CropShape) -> Self {
+        Self {
+            shape,
+            invert: true,
+        }
+    }
+
+    /// Whether a Gaussian with its mean at `point` is kept.
+    pub fn contains(&self, point: &Point3<f32>) -> bool {
+        let uniform = CropUniform::from(self);
+        let local = Matrix4::from_column_slice(&uniform.matrix) * point.to_homogeneous();
+
+        let inside = match uniform.kind {
+            CROP_BOX => local.xyz().amax() <= 1.0,
+            CROP_SPHERE => local.xyz().norm_squared() <= 1.0,
+            _ => local.z >= 0.0,
+        };
+        inside != self.invert
+    }
+}
+
+const CROP_NONE: u32 = 0;
+const CROP_BOX: u32 = 1;
+const CROP_SPHERE: u32 = 2;
+const CROP_PLANE: u32 = 3;
+
+/// `Crop` of preprocess.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct CropUniform {
+    /// world to volume, where a box is [-1, 1]³, a sphere the unit ball and a plane z >= 0
+    matrix: [f32; 16],
+    kind: u32,
+    invert: u32,
+    _pad: [u32; 2],
+}
+
+unsafe impl bytemuck::Zeroable for CropUniform {}
+unsafe impl bytemuck::Pod for CropUniform {}
+
+impl CropUniform {
+    /// Keeps everything, the crop buffer holds one when there are no volumes.
+    pub const NONE: Self = Self {
+        matrix: [0.0; 16],
+        kind: CROP_NONE,
+        invert: 0,
+        _pad: [0; 2],
+    };
+}
+
+impl From<&Crop> for CropUniform {
+    fn from(crop: &Crop) -> Self {
+        let (kind, matrix) = match &crop.shape {
+            CropShape::Box { min, max } => (
+                CROP_BOX,
+                Matrix4::new_nonuniform_scaling(&(2.0 * (max - min).map(|x| 1.0 / x)))
+                    * Matrix4::new_translation(&-nalgebra::center(min, max).coords),
+            ),
+            CropShape::OrientedBox {
+                center,
+                half_extent,
+                rotation,
+            } => (
+                CROP_BOX,
+                Matrix4::new_nonuniform_scaling(&half_extent.map(|x| 1.0 / x))
+                    * rotation.inverse().to_homogeneous()
+                    * Matrix4::new_translation(&-center.coords),
+            ),
+            CropShape::Sphere { center, radius } => (
+                CROP_SPHERE,
+                Matrix4::new_scaling(1.0 / radius) * Matrix4::new_translation(&-center.coords),
+            ),
+            CropShape::Plane { normal, distance } => {
+                let mut matrix = Matrix4::zeros();
+                matrix.set_row(
+                    2,
+                    &Vector4::new(normal.x, normal.y, normal.z, *distance).transpose(),
+                );
+                matrix[(3, 3)] = 1.0;
+                (CROP_PLANE, matrix)
+            }
+        };
+
+        Self {
+            matrix: matrix.as_slice().try_into().unwrap(),
+            kind,
+            invert: crop.invert as u32,
+            _pad: [0; 2],
+        }
+    }
+}
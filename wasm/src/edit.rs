@@ -0,0 +1,334 @@
+use std::{borrow::Cow, ops::RangeInclusive};
+
+use nalgebra::{Vector2, Vector3};
+use wasm_bindgen::prelude::*;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    crop::CropUniform,
+    renderer::{dispatch_size, FAR, NEAR},
+    Camera, Crop,
+};
+
+/// Bytes per Gaussian of the state buffer, a u32 of `STATE_*` bits of edit.wgsl.
+pub(crate) const STATE_SIZE: u64 = 4;
+
+pub(crate) const STATE_DELETED: u32 = 4;
+
+const WG_SIZE: u64 = 64;
+
+/// Gaussians to select, see [`crate::Renderer::select`]. Hidden and deleted Gaussians are
+/// never selected, a Gaussian is selected when any of its instances is.
+#[derive(Clone, Debug)]
+pub enum Selector {
+    /// Means inside the rectangle between `min` and `max` in pixels of the renderer's view
+    /// size. Fisheye and panorama cameras are treated as pinhole cameras.
+    Rect {
+        camera: Camera,
+        min: Vector2<f32>,
+        max: Vector2<f32>,
+    },
+    /// Means inside the polygon `points` in pixels, like [`Selector::Rect`].
+    Lasso {
+        camera: Camera,
+        points: Vec<Vector2<f32>>,
+    },
+    /// Means the crop volume keeps.
+    Crop(Crop),
+    Opacity(RangeInclusive<f32>),
+    /// Largest axis of the Gaussian in world space.
+    Scale(RangeInclusive<f32>),
+}
+
+/// How a selection combines with the current one.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectMode {
+    Replace,
+    Add,
+    Subtract,
+}
+
+const SELECT_RECT: u32 = 0;
+const SELECT_LASSO: u32 = 1;
+const SELECT_CROP: u32 = 2;
+const SELECT_OPACITY: u32 = 3;
+const SELECT_SCALE: u32 = 4;
+
+/// `OP_*` of edit.wgsl
+#[derive(Clone, Copy)]
+pub(crate) enum Op {
+    Add = 0,
+    Subtract = 1,
+    Clear = 2,
+    Hide = 3,
+    Show = 4,
+    Delete = 5,
+    Recolor = 6,
+}
+
+/// `Edit` of edit.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct EditUniform {
+    view_proj: [f32; 16],
+    crop: CropUniform,
+    rect: [f32; 4],
+    opacity: [f32; 2],
+    scale: [f32; 2],
+    screen: [f32; 2],
+    selector: u32,
+    op: u32,
+    color: [f32; 4],
+    num_splat: u32,
+    _pad: [u32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for EditUniform {}
+unsafe impl bytemuck::Pod for EditUniform {}
+
+impl EditUniform {
+    fn new(op: Op, num_splat: u64) -> Self {
+        Self {
+            view_proj: [0.0; 16],
+            crop: CropUniform::NONE,
+            rect: [0.0; 4],
+            opacity: [0.0; 2],
+            scale: [0.0; 2],
+            screen: [0.0; 2],
+            selector: 0,
+            op: op as u32,
+            color: [0.0; 4],
+            num_splat: num_splat as u32,
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// Buffers of the renderer an edit works on.
+pub(crate) struct EditBuffers<'a> {
+    pub gaussians: &'a wgpu::Buffer,
+    pub state: &'a wgpu::Buffer,
+    pub objects: &'a wgpu::Buffer,
+    pub num_gaussian: u64,
+    pub num_splat: u64,
+    /// view size in pixels
+    pub screen: Vector2<u32>,
+}
+
+/// Selects, hides, deletes and recolors Gaussians in place, with the state buffer holding
+/// their selection and visibility.
+pub(crate) struct Editor {
+    bind_group_layout: wgpu::BindGroupLayout,
+    select_pipeline: wgpu::ComputePipeline,
+    apply_pipeline: wgpu::ComputePipeline,
+}
+
+impl Editor {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("edit bind group layout"),
+            entries: &[
+                storage(0, false),
+                storage(1, false),
+                storage(2, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(4, true),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("edit pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("edit compute shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/edit.wgsl"))),
+        });
+
+        let pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+            })
+        };
+
+        Self {
+            select_pipeline: pipeline("select pipeline", "select_gaussians"),
+            apply_pipeline: pipeline("apply edit pipeline", "apply_edit"),
+            bind_group_layout,
+        }
+    }
+
+    /// Selects the Gaussians of `selector`, combined with the current selection by `mode`.
+    pub fn select(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffers: &EditBuffers,
+        selector: &Selector,
+        mode: SelectMode,
+    ) {
+        if mode == SelectMode::Replace {
+            self.apply(device, queue, buffers, Op::Clear, Vector3::zeros());
+        }
+
+        let op = if mode == SelectMode::Subtract {
+            Op::Subtract
+        } else {
+            Op::Add
+        };
+        let mut uniform = EditUniform::new(op, buffers.num_splat);
+        let screen = buffers.screen.cast::<f32>();
+        uniform.screen = screen.into();
+
+        let view_proj = |camera: &Camera| {
+            let matrix =
+                camera.projection(buffers.screen.x, buffers.screen.y, NEAR, FAR) * camera.view;
+            matrix.as_slice().try_into().unwrap()
+        };
+
+        let mut lasso = vec![Vector2::zeros()];
+        match selector {
+            Selector::Rect { camera, min, max } => {
+                uniform.selector = SELECT_RECT;
+                uniform.view_proj = view_proj(camera);
+                uniform.rect = [min.x, min.y, max.x, max.y];
+            }
+            Selector::Lasso { camera, points } => {
+                uniform.selector = SELECT_LASSO;
+                uniform.view_proj = view_proj(camera);
+                if !points.is_empty() {
+                    lasso.clone_from(points);
+                }
+            }
+            Selector::Crop(crop) => {
+                uniform.selector = SELECT_CROP;
+                uniform.crop = CropUniform::from(crop);
+            }
+            Selector::Opacity(range) => {
+                uniform.selector = SELECT_OPACITY;
+                uniform.opacity = [*range.start(), *range.end()];
+            }
+            Selector::Scale(range) => {
+                uniform.selector = SELECT_SCALE;
+                uniform.scale = [*range.start(), *range.end()];
+            }
+        }
+
+        self.dispatch(
+            device,
+            queue,
+            buffers,
+            &uniform,
+            &lasso,
+            &self.select_pipeline,
+            buffers.num_splat,
+        );
+    }
+
+    /// Applies `op` to the selected Gaussians, `color` is the flat rgb of [`Op::Recolor`].
+    pub fn apply(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffers: &EditBuffers,
+        op: Op,
+        color: Vector3<f32>,
+    ) {
+        let mut uniform = EditUniform::new(op, buffers.num_splat);
+        uniform.color = color.push(1.0).into();
+
+        self.dispatch(
+            device,
+            queue,
+            buffers,
+            &uniform,
+            &[Vector2::zeros()],
+            &self.apply_pipeline,
+            buffers.num_gaussian,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffers: &EditBuffers,
+        uniform: &EditUniform,
+        lasso: &[Vector2<f32>],
+        pipeline: &wgpu::ComputePipeline,
+        num_thread: u64,
+    ) {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("edit"),
+            contents: bytemuck::bytes_of(uniform),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let points: Vec<[f32; 2]> = lasso.iter().map(|point| (*point).into()).collect();
+        let lasso_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("lasso"),
+            contents: bytemuck::cast_slice(&points),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("edit bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                buffers.gaussians.as_entire_binding(),
+                buffers.state.as_entire_binding(),
+                buffers.objects.as_entire_binding(),
+                uniform_buffer.as_entire_binding(),
+                lasso_buffer.as_entire_binding(),
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource,
+            })
+            .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("edit encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("edit"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let (x, y) = dispatch_size(num_thread, WG_SIZE);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
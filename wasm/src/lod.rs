@@ -0,0 +1,721 @@
+//! Level of detail hierarchy built offline from the Gaussians of a scene.
+//!
+//! An octree over the means groups up to [`MAX_CHILDREN`] Gaussians, or nodes, under a
+//! coarser Gaussian matching their mean, covariance and coverage. Every frame, preprocess
+//! draws the cut of the tree where the bounding spheres of the nodes project to at most
+//! `RenderSettings::lod_threshold` pixels, so distant parts of a large scene are sorted and
+//! rasterized as a few large splats instead of many small ones.
+//!
+//! A [`LodCut`] bounds the memory and the per frame work of a large hierarchy: it walks the
+//! tree on the CPU and keeps only the Gaussians of the cut in a renderer object of fixed size.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use nalgebra::{
+    Matrix3, Matrix4, Point3, Quaternion, Rotation3, SymmetricEigen, UnitQuaternion, Vector3,
+};
+
+use crate::{
+    ply::{self, GAUSSIAN_FLOATS},
+    renderer::BYTES_PER_GAUSSIAN,
+    Camera, CameraModel, Renderer,
+};
+
+/// Most children merged into a node.
+pub const MAX_CHILDREN: usize = 8;
+
+/// Octree levels before Gaussians at the same position are merged all together.
+const MAX_DEPTH: u32 = 24;
+
+/// Bytes per Gaussian of the LOD buffer, a `Lod` of preprocess.wgsl.
+pub(crate) const LOD_SIZE: u64 = 32;
+
+const MAGIC: &[u8; 8] = b"gslod002";
+
+/// Parent index of a root.
+const NO_PARENT: u32 = u32::MAX;
+
+/// Bytes per Gaussian of a `.lod` file after the header: the Gaussian, its `LodUniform` and
+/// its parent index.
+const NODE_BYTES: usize = (GAUSSIAN_FLOATS + 8) * 4 + 4;
+
+/// `Lod` of preprocess.wgsl, bounding spheres in object space
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LodUniform {
+    /// center and radius, 0 for a leaf
+    sphere: [f32; 4],
+    /// sphere of the parent, a negative radius for a root
+    parent: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for LodUniform {}
+unsafe impl bytemuck::Pod for LodUniform {}
+
+impl LodUniform {
+    /// Root leaf, drawn at any threshold. Gaussians outside of a hierarchy get this one.
+    pub const NONE: Self = Self {
+        sphere: [0.0; 4],
+        parent: [0.0, 0.0, 0.0, -1.0],
+    };
+}
+
+/// Gaussians of a scene followed by the coarser ones merged from them, for
+/// [`crate::Renderer::add_lod_object`].
+pub struct LodTree {
+    /// Packed `Gaussian` structs, the leaves in their original order first.
+    pub gaussians: Vec<f32>,
+    pub num_gaussian: u64,
+    /// Gaussians of the scene it was built from.
+    pub num_leaf: u64,
+    /// The leaves have the 3D filter of Mip-Splatting.
+    pub mip_splatting: bool,
+    nodes: Vec<LodUniform>,
+    /// index of the parent of every node, [`NO_PARENT`] for a root
+    parents: Vec<u32>,
+}
+
+impl LodTree {
+    /// Merges `num_gaussian` packed `Gaussian` structs, e.g. of a [`ply::PointCloud`], into a
+    /// hierarchy.
+    pub fn build(gaussians: &[f32], num_gaussian: u64, mip_splatting: bool) -> Self {
+        let num_leaf = num_gaussian as usize;
+        let mut builder = Builder {
+            gaussians: gaussians[..num_leaf * GAUSSIAN_FLOATS].to_vec(),
+            nodes: vec![LodUniform::NONE; num_leaf],
+            parents: vec![NO_PARENT; num_leaf],
+        };
+
+        if num_leaf > 0 {
+            let means: Vec<Point3<f32>> = (0..num_leaf).map(|i| builder.mean(i)).collect();
+            let (min, max) = means.iter().fold((means[0], means[0]), |(min, max), mean| {
+                (min.inf(mean), max.sup(mean))
+            });
+            let size = (max - min).max();
+            builder.node(&means, (0..num_leaf).collect(), min, size, 0);
+        }
+
+        Self {
+            num_gaussian: builder.nodes.len() as u64,
+            num_leaf: num_gaussian,
+            mip_splatting,
+            gaussians: builder.gaussians,
+            nodes: builder.nodes,
+            parents: builder.parents,
+        }
+    }
+
+    pub(crate) fn nodes(&self) -> &[LodUniform] {
+        &self.nodes
+    }
+
+    fn gaussian(&self, index: usize) -> &[f32] {
+        &self.gaussians[index * GAUSSIAN_FLOATS..(index + 1) * GAUSSIAN_FLOATS]
+    }
+
+    /// Little endian binary file read back by [`LodTree::load`].
+    pub fn save(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(28 + self.nodes.len() * NODE_BYTES);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.num_gaussian.to_le_bytes());
+        bytes.extend_from_slice(&self.num_leaf.to_le_bytes());
+        bytes.extend_from_slice(&(self.mip_splatting as u32).to_le_bytes());
+        let floats = self
+            .nodes
+            .iter()
+            .flat_map(|node| node.sphere.iter().chain(&node.parent));
+        for value in self.gaussians.iter().chain(floats) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for parent in &self.parents {
+            bytes.extend_from_slice(&parent.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn load(bytes: &[u8]) -> Result<Self, String> {
+        let (magic, bytes) = bytes.split_at(bytes.len().min(MAGIC.len()));
+        if magic != MAGIC {
+            return Err("not a Gaussian LOD file".to_string());
+        }
+
+        let header = bytes.get(..20).ok_or("truncated LOD header")?;
+        let num_gaussian = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let num_leaf = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let mip_splatting = u32::from_le_bytes(header[16..].try_into().unwrap()) != 0;
+
+        let size = usize::try_from(num_gaussian)
+            .ok()
+            .and_then(|num_gaussian| num_gaussian.checked_mul(NODE_BYTES))
+            .filter(|&size| size == bytes.len() - 20);
+        if size.is_none() || num_leaf > num_gaussian {
+            return Err(format!(
+                "LOD file of {num_gaussian} Gaussians has {} bytes",
+                bytes.len() - 20
+            ));
+        }
+
+        let num_float = num_gaussian as usize * (GAUSSIAN_FLOATS + 8);
+        let (floats, parents) = bytes[20..].split_at(num_float * 4);
+        let floats: Vec<f32> = floats
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let parents: Vec<u32> = parents
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        // parents come after their children
+        if parents.iter().enumerate().any(|(node, &parent)| {
+            parent != NO_PARENT && (parent as usize <= node || parent as u64 >= num_gaussian)
+        }) {
+            return Err("LOD file has a parent out of order".to_string());
+        }
+
+        let (gaussians, nodes) = floats.split_at(num_gaussian as usize * GAUSSIAN_FLOATS);
+        let nodes = nodes
+            .chunks_exact(8)
+            .map(|node| LodUniform {
+                sphere: node[..4].try_into().unwrap(),
+                parent: node[4..].try_into().unwrap(),
+            })
+            .collect();
+
+        Ok(Self {
+            gaussians: gaussians.to_vec(),
+            num_gaussian,
+            num_leaf,
+            mip_splatting,
+            nodes,
+            parents,
+        })
+    }
+}
+
+/// Keeps the cut of a [`LodTree`] for the camera in a renderer object of fixed size, instead of
+/// uploading the whole hierarchy with [`Renderer::add_lod_object`]. The cut is refined largest
+/// node first down to [`crate::RenderSettings::lod_threshold`] pixels as far as the object has
+/// room, so GPU memory and per frame work stay within the budget however large the tree.
+///
+/// The object holds the nodes of the cut without their hierarchy, so preprocess draws all of
+/// them and the two ways of drawing a tree are not meant to be combined on one tree.
+pub struct LodCut {
+    tree: LodTree,
+    /// children of every node, none for a leaf
+    children: Vec<Vec<u32>>,
+    roots: Vec<u32>,
+    /// renderer object holding the cut
+    object: usize,
+    /// node in every slot of the object
+    slots: Vec<Option<u32>>,
+    /// slot of every node of the cut
+    resident: Vec<Option<u32>>,
+}
+
+impl LodCut {
+    /// Reserves an object in `renderer` for as many Gaussians of `tree` as `budget` bytes of
+    /// GPU memory hold, at least one, accounted like [`crate::Streamer::new`]. Nothing is
+    /// drawn before the first [`LodCut::update`].
+    pub fn new(
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tree: LodTree,
+        budget: u64,
+    ) -> Self {
+        let budget = budget.saturating_sub(renderer.scene_size());
+        let capacity = (budget / BYTES_PER_GAUSSIAN).clamp(1, tree.num_gaussian.max(1));
+        let object = renderer.reserve_objects(device, queue, 1, capacity).start;
+
+        Self::with_capacity(tree, object, capacity as usize)
+    }
+
+    fn with_capacity(tree: LodTree, object: usize, capacity: usize) -> Self {
+        let mut children = vec![Vec::new(); tree.parents.len()];
+        let mut roots = Vec::new();
+        for (node, &parent) in tree.parents.iter().enumerate() {
+            match parent {
+                NO_PARENT => roots.push(node as u32),
+                parent => children[parent as usize].push(node as u32),
+            }
+        }
+
+        Self {
+            resident: vec![None; tree.parents.len()],
+            slots: vec![None; capacity],
+            tree,
+            children,
+            roots,
+            object,
+        }
+    }
+
+    pub fn tree(&self) -> &LodTree {
+        &self.tree
+    }
+
+    /// Renderer object holding the cut, placed with [`Renderer::set_object`].
+    pub fn object(&self) -> usize {
+        self.object
+    }
+
+    /// Walks the hierarchy for `camera` and uploads the Gaussians entering the cut into the
+    /// room of the ones leaving it, drawn from the next frame on. Returns the size of the cut.
+    pub fn update(&mut self, renderer: &Renderer, queue: &wgpu::Queue, camera: &Camera) -> usize {
+        let object = renderer.object(self.object).expect("the object of the cut");
+        let transform = renderer.transform() * &object.transform;
+        let matrix = transform.matrix();
+        let radius = |node: u32| {
+            projected_radius(
+                &self.tree.nodes[node as usize].sphere,
+                &matrix,
+                transform.scale,
+                camera,
+            )
+        };
+        let cut = self.cut(radius, renderer.settings().lod_threshold);
+
+        let mut sorted = cut.clone();
+        sorted.sort_unstable();
+        let mut evicted = Vec::new();
+        for (slot, node) in self.slots.iter_mut().enumerate() {
+            if node.is_some_and(|node| sorted.binary_search(&node).is_err()) {
+                self.resident[node.unwrap() as usize] = None;
+                *node = None;
+                evicted.push(slot as u64);
+            }
+        }
+        for slots in runs(&evicted) {
+            renderer.clear_gaussians(queue, self.object, slots);
+        }
+
+        let mut free = (0..self.slots.len()).filter(|&slot| self.slots[slot].is_none());
+        let mut written = Vec::new();
+        for &node in &cut {
+            if self.resident[node as usize].is_some() {
+                continue;
+            }
+            // the cut fits in the object
+            let slot = free.next().expect("a free slot for every node of the cut");
+            written.push((slot as u64, node));
+        }
+        for &(slot, node) in &written {
+            self.slots[slot as usize] = Some(node);
+            self.resident[node as usize] = Some(slot as u32);
+        }
+
+        let slots: Vec<u64> = written.iter().map(|(slot, _)| *slot).collect();
+        let mut written = written.iter();
+        for slots in runs(&slots) {
+            let gaussians: Vec<f32> = written
+                .by_ref()
+                .take((slots.end - slots.start) as usize)
+                .flat_map(|&(_, node)| self.tree.gaussian(node as usize).iter().copied())
+                .collect();
+            renderer.write_gaussians(queue, self.object, slots.start, &gaussians);
+        }
+
+        cut.len()
+    }
+
+    /// Nodes drawn for the projected radius `radius` of every node, at most one per path from
+    /// a root to a leaf and at most as many as the object has room for.
+    fn cut(&self, radius: impl Fn(u32) -> f32, threshold: f32) -> Vec<u32> {
+        let capacity = self.slots.len();
+        let mut candidates: BinaryHeap<Candidate> = self
+            .roots
+            .iter()
+            .map(|&node| Candidate {
+                radius: radius(node),
+                node,
+            })
+            .collect();
+        let mut count = candidates.len();
+
+        let mut cut = Vec::with_capacity(capacity);
+        while let Some(Candidate { radius: size, node }) = candidates.pop() {
+            let children = &self.children[node as usize];
+            if size <= threshold || children.is_empty() || count + children.len() - 1 > capacity {
+                cut.push(node);
+                continue;
+            }
+
+            count += children.len() - 1;
+            candidates.extend(children.iter().map(|&child| Candidate {
+                radius: radius(child),
+                node: child,
+            }));
+        }
+        cut.truncate(capacity);
+
+        cut
+    }
+}
+
+/// Node of a [`LodCut`] ordered by its projected radius.
+struct Candidate {
+    radius: f32,
+    node: u32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.radius.total_cmp(&other.radius)
+    }
+}
+
+/// Upper bound of the radius in pixels of a bounding sphere of an object placed by `matrix`
+/// and scaled by `scale`, 0 for a leaf. `projected_radius` of preprocess.wgsl.
+fn projected_radius(sphere: &[f32; 4], matrix: &Matrix4<f32>, scale: f32, camera: &Camera) -> f32 {
+    if sphere[3] <= 0.0 {
+        return 0.0;
+    }
+
+    let radius = scale * sphere[3];
+    let pixels = radius * camera.focal.max();
+    if camera.model == CameraModel::Orthographic {
+        return pixels;
+    }
+
+    let center = matrix.transform_point(&Point3::new(sphere[0], sphere[1], sphere[2]));
+    pixels / (nalgebra::distance(&center, &camera.position()) - radius).max(0.01)
+}
+
+/// Runs of consecutive values of the ascending `values`.
+fn runs(values: &[u64]) -> impl Iterator<Item = std::ops::Range<u64>> + '_ {
+    let mut values = values.iter().peekable();
+    std::iter::from_fn(move || {
+        let start = *values.next()?;
+        let mut end = start + 1;
+        while values.next_if_eq(&&end).is_some() {
+            end += 1;
+        }
+        Some(start..end)
+    })
+}
+
+struct Builder {
+    gaussians: Vec<f32>,
+    nodes: Vec<LodUniform>,
+    parents: Vec<u32>,
+}
+
+impl Builder {
+    fn gaussian(&self, index: usize) -> &[f32] {
+        &self.gaussians[index * GAUSSIAN_FLOATS..(index + 1) * GAUSSIAN_FLOATS]
+    }
+
+    fn mean(&self, index: usize) -> Point3<f32> {
+        Point3::from_slice(&self.gaussian(index)[ply::MEAN..ply::MEAN + 3])
+    }
+
+    /// Node of the Gaussians `items` inside the cube at `min` of edge `size`, returns the
+    /// Gaussian drawn for it.
+    fn node(
+        &mut self,
+        means: &[Point3<f32>],
+        items: Vec<usize>,
+        min: Point3<f32>,
+        size: f32,
+        depth: u32,
+    ) -> usize {
+        if items.len() == 1 {
+            return items[0];
+        }
+
+        let children = if items.len() <= MAX_CHILDREN || depth == MAX_DEPTH {
+            items
+        } else {
+            let half = 0.5 * size;
+            let center = min + Vector3::repeat(half);
+            let mut octants = vec![Vec::new(); 8];
+            for item in items {
+                let mean = means[item];
+                let octant = (mean.x >= center.x) as usize
+                    | ((mean.y >= center.y) as usize) << 1
+                    | ((mean.z >= center.z) as usize) << 2;
+                octants[octant].push(item);
+            }
+
+            let children: Vec<usize> = octants
+                .into_iter()
+                .enumerate()
+                .filter(|(_, octant)| !octant.is_empty())
+                .map(|(octant, items)| {
+                    let offset = Vector3::new(
+                        (octant & 1) as f32,
+                        ((octant >> 1) & 1) as f32,
+                        ((octant >> 2) & 1) as f32,
+                    );
+                    self.node(means, items, min + offset * half, half, depth + 1)
+                })
+                .collect();
+
+            // a single occupied octant needs no node of its own
+            if children.len() == 1 {
+                return children[0];
+            }
+            children
+        };
+
+        self.merge(&children)
+    }
+
+    /// Appends the Gaussian matching the moments of `children`, weighted by their opacity
+    /// times their area, and makes it their parent.
+    fn merge(&mut self, children: &[usize]) -> usize {
+        let weights: Vec<f32> = children
+            .iter()
+            .map(|&child| {
+                let gaussian = self.gaussian(child);
+                gaussian[ply::OPACITY] * area(scale(gaussian)).max(f32::MIN_POSITIVE)
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        // fully transparent children count the same
+        let weights: Vec<f32> = if total > 0.0 {
+            weights.iter().map(|weight| weight / total).collect()
+        } else {
+            vec![1.0 / children.len() as f32; children.len()]
+        };
+
+        let mut merged = vec![0.0; GAUSSIAN_FLOATS];
+        for (&child, weight) in children.iter().zip(&weights) {
+            for (value, child) in merged.iter_mut().zip(self.gaussian(child)) {
+                *value += weight * child;
+            }
+        }
+
+        let mean = Point3::from_slice(&merged[ply::MEAN..ply::MEAN + 3]);
+        let covariance: Matrix3<f32> = children
+            .iter()
+            .zip(&weights)
+            .map(|(&child, weight)| {
+                let offset = self.mean(child) - mean;
+                (covariance(self.gaussian(child)) + offset * offset.transpose()) * *weight
+            })
+            .sum();
+
+        let eigen = SymmetricEigen::new(covariance);
+        let mut axes = eigen.eigenvectors;
+        if axes.determinant() < 0.0 {
+            axes.set_column(2, &-axes.column(2));
+        }
+        let merged_scale = eigen.eigenvalues.map(|value| value.max(1e-12).sqrt());
+        let rotation =
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(axes));
+
+        merged[ply::SCALE..ply::SCALE + 3].copy_from_slice(merged_scale.as_slice());
+        merged[ply::ROTATION..ply::ROTATION + 4]
+            .copy_from_slice(&[rotation.w, rotation.i, rotation.j, rotation.k]);
+        // the coverage of the children, spread over the merged area
+        let coverage: f32 = children
+            .iter()
+            .map(|&child| {
+                let gaussian = self.gaussian(child);
+                gaussian[ply::OPACITY] * area(scale(gaussian))
+            })
+            .sum();
+        merged[ply::OPACITY] = (coverage / area(merged_scale)).min(1.0);
+
+        let radius = children
+            .iter()
+            .map(|&child| {
+                nalgebra::distance(&self.mean(child), &mean) + self.nodes[child].sphere[3]
+            })
+            .fold(0.0, f32::max);
+        let sphere = [mean.x, mean.y, mean.z, radius];
+
+        let index = self.nodes.len();
+        for &child in children {
+            self.nodes[child].parent = sphere;
+            self.parents[child] = index as u32;
+        }
+        self.nodes.push(LodUniform {
+            sphere,
+            parent: LodUniform::NONE.parent,
+        });
+        self.parents.push(NO_PARENT);
+        self.gaussians.extend_from_slice(&merged);
+
+        index
+    }
+}
+
+fn scale(gaussian: &[f32]) -> Vector3<f32> {
+    Vector3::from_column_slice(&gaussian[ply::SCALE..ply::SCALE + 3])
+}
+
+/// Product of the two largest axes, proportional to the largest projected area.
+fn area(scale: Vector3<f32>) -> f32 {
+    (scale.x * scale.y)
+        .max(scale.x * scale.z)
+        .max(scale.y * scale.z)
+}
+
+fn covariance(gaussian: &[f32]) -> Matrix3<f32> {
+    let [w, x, y, z] = gaussian[ply::ROTATION..ply::ROTATION + 4]
+        .try_into()
+        .unwrap();
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+        .to_rotation_matrix()
+        .into_inner();
+    let variance = Matrix3::from_diagonal(&scale(gaussian).component_mul(&scale(gaussian)));
+
+    rotation * variance * rotation.transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n`³ small Gaussians on a grid of unit spacing.
+    fn grid(n: usize) -> Vec<f32> {
+        let mut gaussians = Vec::new();
+        for i in 0..n * n * n {
+            let mut gaussian = [0.0; GAUSSIAN_FLOATS];
+            gaussian[ply::MEAN..ply::MEAN + 3].copy_from_slice(&[
+                (i % n) as f32,
+                (i / n % n) as f32,
+                (i / n / n) as f32,
+            ]);
+            gaussian[ply::SCALE..ply::SCALE + 3].copy_from_slice(&[0.1, 0.2, 0.3]);
+            gaussian[ply::OPACITY] = 0.5;
+            gaussian[ply::ROTATION] = 1.0;
+            gaussians.extend_from_slice(&gaussian);
+        }
+        gaussians
+    }
+
+    fn tree(n: usize) -> LodTree {
+        LodTree::build(&grid(n), (n * n * n) as u64, true)
+    }
+
+    #[test]
+    fn build_keeps_leaves_first() {
+        let gaussians = grid(4);
+        let tree = LodTree::build(&gaussians, 64, true);
+
+        assert_eq!(tree.num_leaf, 64);
+        assert!(tree.num_gaussian > tree.num_leaf);
+        assert_eq!(
+            tree.gaussians.len() as u64,
+            tree.num_gaussian * GAUSSIAN_FLOATS as u64
+        );
+        assert_eq!(tree.gaussians[..gaussians.len()], gaussians);
+        assert_eq!(
+            tree.nodes
+                .iter()
+                .filter(|node| node.parent[3] < 0.0)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn parents_bound_their_children() {
+        let tree = tree(4);
+        for (node, &parent) in tree.parents.iter().enumerate() {
+            if parent == NO_PARENT {
+                assert!(tree.nodes[node].parent[3] < 0.0);
+                continue;
+            }
+            assert!(parent as usize > node);
+            assert_eq!(tree.nodes[node].parent, tree.nodes[parent as usize].sphere);
+        }
+        assert_eq!(
+            tree.parents
+                .iter()
+                .filter(|&&parent| parent == NO_PARENT)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let tree = tree(3);
+        let loaded = LodTree::load(&tree.save()).unwrap();
+
+        assert_eq!(loaded.num_gaussian, tree.num_gaussian);
+        assert_eq!(loaded.num_leaf, tree.num_leaf);
+        assert_eq!(loaded.mip_splatting, tree.mip_splatting);
+        assert_eq!(loaded.gaussians, tree.gaussians);
+        assert_eq!(loaded.parents, tree.parents);
+        for (loaded, node) in loaded.nodes.iter().zip(&tree.nodes) {
+            assert_eq!(loaded.sphere, node.sphere);
+            assert_eq!(loaded.parent, node.parent);
+        }
+    }
+
+    #[test]
+    fn load_rejects_bad_files() {
+        let bytes = tree(2).save();
+        assert!(LodTree::load(&bytes[..bytes.len() - 4]).is_err());
+        assert!(LodTree::load(b"gslod000").is_err());
+
+        // the last node is the root, make the first leaf its own parent
+        let mut bytes = bytes;
+        let parents = bytes.len() - tree(2).parents.len() * 4;
+        bytes[parents..parents + 4].copy_from_slice(&0_u32.to_le_bytes());
+        assert!(LodTree::load(&bytes).is_err());
+    }
+
+    #[test]
+    fn cut_refines_within_capacity() {
+        let tree = tree(4);
+        let num_leaf = tree.num_leaf as usize;
+        let radius = |cut: &LodCut, node: u32| cut.tree.nodes[node as usize].sphere[3];
+
+        // a cut covers every leaf once
+        let covers_leaves = |cut: &LodCut, nodes: &[u32]| {
+            (0..num_leaf as u32).all(|leaf| {
+                let mut node = leaf;
+                let mut count = 0;
+                loop {
+                    count += nodes.contains(&node) as usize;
+                    match cut.tree.parents[node as usize] {
+                        NO_PARENT => return count == 1,
+                        parent => node = parent,
+                    }
+                }
+            })
+        };
+
+        let cut = LodCut::with_capacity(tree, 0, num_leaf);
+        let leaves = cut.cut(|node| radius(&cut, node), 0.0);
+        assert_eq!(leaves.len(), num_leaf);
+        assert!(covers_leaves(&cut, &leaves));
+
+        let roots = cut.cut(|node| radius(&cut, node), f32::INFINITY);
+        assert_eq!(roots, cut.roots);
+
+        let tree = cut.tree;
+        let cut = LodCut::with_capacity(tree, 0, 10);
+        let nodes = cut.cut(|node| radius(&cut, node), 0.0);
+        assert!(nodes.len() <= 10);
+        assert!(covers_leaves(&cut, &nodes));
+    }
+
+    #[test]
+    fn runs_of_consecutive_values() {
+        let runs: Vec<_> = runs(&[0, 1, 2, 5, 7, 8]).collect();
+        assert_eq!(runs, [0..3, 5..6, 7..9]);
+        assert_eq!(super::runs(&[]).count(), 0);
+    }
+}
@@ -1,3 +1,537 @@
+//! Headless renderer:
+//! `gs <point_cloud.ply | scene.lod | scene.chunks> <cameras.json> [--camera N]
+//! [--path keyframes.json | --interpolate | --orbit] [--frames N] [--panorama |
+//! --orthographic] [--lod PIXELS] [--build-lod OUT] [--budget MB] [--build-chunks OUT]
+//! [--out PATH] [--pipe COMMAND] [--profile] [--stats] [--json]`
+//!
+//! Renders the dataset camera, or `--frames` cameras at its resolution along a sequence: a
+//! keyframe path, a path through all dataset cameras, or an orbit of the dataset camera around
+//! the point the dataset cameras look at. Frames are written as PNG, to `--out` (a file, or a
+//! directory of `frame_00000.png`, ... for a sequence) and/or to the stdin of `--pipe`, e.g.
+//! `--pipe "ffmpeg -f image2pipe -framerate 30 -i - out.mp4"`. `--panorama` renders 360°
+//! equirectangular images twice as wide as the dataset camera is tall instead, and
+//! `--orthographic` parallel projections keeping the scale at the focus of the dataset cameras.
+//!
+//! `--build-lod` writes the level of detail hierarchy of the point cloud to a `.lod` file,
+//! loaded in its place later on, and `--lod` draws the coarser levels of a hierarchy where
+//! their nodes project to at most `PIXELS`, building one first from a point cloud. Only as
+//! much of the hierarchy as fits in `--budget` megabytes of GPU memory is drawn.
+//!
+//! `--build-chunks` splits the point cloud into a `.chunks` file, rendered by streaming the
+//! chunks nearest to every camera into `--budget` megabytes of GPU memory.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+use gs::{
+    chunk_gaussians, orbit, ply, read_texture, Camera, CameraModel, CameraPath, ChunkIndex,
+    DatasetCamera, Keyframe, LodCut, LodTree, RenderSettings, RenderTarget, Renderer, Streamer,
+};
+
+const USAGE: &str = "usage: gs <point_cloud.ply | scene.lod | scene.chunks> <cameras.json> \
+                     [--camera N] [--path keyframes.json | --interpolate | --orbit] \
+                     [--frames N] [--panorama | --orthographic] [--lod PIXELS] \
+                     [--build-lod OUT] [--budget MB] [--build-chunks OUT] [--out PATH] \
+                     [--pipe COMMAND] [--profile] [--stats] [--json]";
+
+/// Gaussians per chunk of `--build-chunks`.
+const CHUNK_SIZE: u64 = 65536;
+
+/// Cameras rendered instead of the single dataset camera.
+enum Sequence {
+    /// keyframes json
+    Path(String),
+    /// through all dataset cameras
+    Interpolate,
+    /// around the focus of the dataset cameras
+    Orbit,
+}
+
+/// Gaussians to render.
+enum Scene {
+    PointCloud(ply::PointCloud),
+    Lod(LodTree),
+    /// streamed from the chunk file
+    Chunks(ChunkIndex, File),
+}
+
+impl Scene {
+    fn load(args: &Args) -> Result<Self, String> {
+        if args.ply.ends_with(".chunks") {
+            let error = |e: String| format!("{}: {e}", args.ply);
+            let mut file = File::open(&args.ply).map_err(|e| error(e.to_string()))?;
+            let mut index = vec![0; ChunkIndex::PREFIX_SIZE];
+            file.read_exact(&mut index)
+                .map_err(|e| error(e.to_string()))?;
+            index.resize(ChunkIndex::size(&index).map_err(error)?, 0);
+            file.read_exact(&mut index[ChunkIndex::PREFIX_SIZE..])
+                .map_err(|e| error(e.to_string()))?;
+            let index = ChunkIndex::load(&index).map_err(error)?;
+            return Ok(Scene::Chunks(index, file));
+        }
+
+        let bytes = std::fs::read(&args.ply).map_err(|e| format!("{}: {e}", args.ply))?;
+        if args.ply.ends_with(".lod") {
+            let tree = LodTree::load(&bytes).map_err(|e| format!("{}: {e}", args.ply))?;
+            return Ok(Scene::Lod(tree));
+        }
+
+        let point_cloud = ply::load(&bytes)?;
+        if let Some(out) = &args.build_chunks {
+            let chunks = ChunkIndex::build(
+                &point_cloud.gaussians,
+                point_cloud.num_gaussian,
+                point_cloud.mip_splatting,
+                CHUNK_SIZE,
+            );
+            std::fs::write(out, chunks).map_err(|e| format!("{out}: {e}"))?;
+        }
+        if args.lod.is_none() && args.build_lod.is_none() {
+            return Ok(Scene::PointCloud(point_cloud));
+        }
+
+        Ok(Scene::Lod(LodTree::build(
+            &point_cloud.gaussians,
+            point_cloud.num_gaussian,
+            point_cloud.mip_splatting,
+        )))
+    }
+
+    /// packed Gaussians, their number and whether they are Mip-Splatting's
+    fn gaussians(&self) -> (&[f32], u64, bool) {
+        match self {
+            Scene::PointCloud(point_cloud) => (
+                &point_cloud.gaussians,
+                point_cloud.num_gaussian,
+                point_cloud.mip_splatting,
+            ),
+            // only the cut of the hierarchy is uploaded, by a `LodCut`
+            Scene::Lod(tree) => (&[], 0, tree.mip_splatting),
+            Scene::Chunks(index, _) => (&[], 0, index.mip_splatting),
+        }
+    }
+}
+
+struct Args {
+    ply: String,
+    cameras: String,
+    camera: usize,
+    sequence: Option<Sequence>,
+    frames: usize,
+    out: Option<String>,
+    pipe: Option<String>,
+    profile: bool,
+    stats: bool,
+    json: bool,
+    panorama: bool,
+    orthographic: bool,
+    /// LOD threshold in pixels
+    lod: Option<f32>,
+    build_lod: Option<String>,
+    /// megabytes of GPU memory for streamed chunks
+    budget: u64,
+    build_chunks: Option<String>,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut paths = Vec::new();
+        let mut camera = 0;
+        let mut sequence = None;
+        let mut frames = 120;
+        let mut out = None;
+        let mut pipe = None;
+        let mut profile = false;
+        let mut stats = false;
+        let mut json = false;
+        let mut panorama = false;
+        let mut orthographic = false;
+        let mut lod = None;
+        let mut build_lod = None;
+        let mut budget = 1024;
+        let mut build_chunks = None;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--camera" => {
+                    camera = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--camera expects an index")?
+                }
+                "--path" | "--interpolate" | "--orbit" if sequence.is_some() => {
+                    return Err("--path, --interpolate and --orbit are exclusive".to_string())
+                }
+                "--path" => {
+                    sequence = Some(Sequence::Path(args.next().ok_or("--path expects a file")?))
+                }
+                "--interpolate" => sequence = Some(Sequence::Interpolate),
+                "--orbit" => sequence = Some(Sequence::Orbit),
+                "--frames" => {
+                    frames = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--frames expects a count")?
+                }
+                "--out" => out = Some(args.next().ok_or("--out expects a path")?),
+                "--pipe" => pipe = Some(args.next().ok_or("--pipe expects a command")?),
+                "--profile" => profile = true,
+                "--stats" => stats = true,
+                "--json" => json = true,
+                "--panorama" | "--orthographic" if panorama || orthographic => {
+                    return Err("--panorama and --orthographic are exclusive".to_string())
+                }
+                "--panorama" => panorama = true,
+                "--orthographic" => orthographic = true,
+                "--lod" => {
+                    lod = Some(
+                        args.next()
+                            .and_then(|n| n.parse().ok())
+                            .ok_or("--lod expects a size in pixels")?,
+                    )
+                }
+                "--build-lod" => build_lod = Some(args.next().ok_or("--build-lod expects a path")?),
+                "--budget" => {
+                    budget = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--budget expects megabytes")?
+                }
+                "--build-chunks" => {
+                    build_chunks = Some(args.next().ok_or("--build-chunks expects a path")?)
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+                _ => paths.push(arg),
+            }
+        }
+
+        let [ply, cameras]: [String; 2] = paths.try_into().map_err(|_| USAGE.to_string())?;
+
+        Ok(Self {
+            ply,
+            cameras,
+            camera,
+            sequence,
+            frames,
+            out,
+            pipe,
+            profile,
+            stats,
+            json,
+            panorama,
+            orthographic,
+            lod,
+            build_lod,
+            budget,
+            build_chunks,
+        })
+    }
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
+    if let Err(e) = pollster::block_on(run(args)) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let scene = Scene::load(&args)?;
+    if let (Some(out), Scene::Lod(tree)) = (&args.build_lod, &scene) {
+        std::fs::write(out, tree.save()).map_err(|e| format!("{out}: {e}"))?;
+    }
+    let (gaussians, num_gaussian, mip_splatting) = scene.gaussians();
+
+    let json =
+        std::fs::read_to_string(&args.cameras).map_err(|e| format!("{}: {e}", args.cameras))?;
+    let cameras = DatasetCamera::load(&json).map_err(|e| format!("{}: {e}", args.cameras))?;
+    let dataset_camera = cameras.get(args.camera).ok_or(format!(
+        "{} has {} cameras",
+        args.cameras,
+        cameras.len()
+    ))?;
+
+    let mut settings = if mip_splatting {
+        RenderSettings::mip_splatting()
+    } else {
+        RenderSettings::new()
+    };
+    settings.profile = args.profile;
+    settings.stats = args.stats;
+    settings.lod_threshold = args.lod.unwrap_or(0.0);
+
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: None,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or("no suitable adapter")?;
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: if settings.profile {
+                    adapter.features() & wgpu::Features::TIMESTAMP_QUERY
+                } else {
+                    wgpu::Features::empty()
+                },
+                required_limits: Renderer::required_limits(&adapter),
+            },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let height = dataset_camera.height;
+    let width = if args.panorama {
+        2 * height
+    } else {
+        dataset_camera.width
+    };
+    let max_size = device.limits().max_texture_dimension_2d;
+    if width > max_size || height > max_size {
+        return Err(format!(
+            "{width}x{height} images are larger than the {max_size} pixels per side the device \
+             supports"
+        ));
+    }
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("target texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut renderer = Renderer::new(
+        &device,
+        &queue,
+        format,
+        gaussians,
+        num_gaussian,
+        width,
+        height,
+        settings,
+    )
+    .await;
+    let mut lod_cut = None;
+    let mut streamer = None;
+    match scene {
+        Scene::Lod(tree) => {
+            lod_cut = Some(LodCut::new(
+                &mut renderer,
+                &device,
+                &queue,
+                tree,
+                args.budget << 20,
+            ))
+        }
+        Scene::Chunks(index, file) => {
+            streamer = Some((
+                Streamer::new(&mut renderer, &device, &queue, index, args.budget << 20),
+                file,
+            ))
+        }
+        Scene::PointCloud(_) => {}
+    }
+
+    let focus = DatasetCamera::focus(&cameras);
+    let mut cameras = match &args.sequence {
+        Some(Sequence::Path(path)) => {
+            let json = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+            let camera_path = CameraPath::load(&json).map_err(|e| format!("{path}: {e}"))?;
+            camera_path.cameras(args.frames, width, height)
+        }
+        Some(Sequence::Interpolate) => CameraPath::through(&cameras)
+            .map_err(|e| format!("{}: {e}", args.cameras))?
+            .cameras(args.frames, width, height),
+        Some(Sequence::Orbit) => orbit(
+            &Keyframe::from(dataset_camera),
+            &focus,
+            &DatasetCamera::up(&cameras),
+            args.frames,
+        )
+        .iter()
+        .map(|keyframe| keyframe.camera(width, height))
+        .collect(),
+        None => vec![dataset_camera.camera()],
+    };
+    if args.panorama {
+        for camera in &mut cameras {
+            camera.model = CameraModel::Equirectangular;
+        }
+    }
+    if args.orthographic {
+        for camera in &mut cameras {
+            // pixels per world unit at the depth of the focus
+            let depth = (camera.view * focus.to_homogeneous()).z;
+            if depth <= 0.0 {
+                return Err("--orthographic needs the scene center in front of the camera".into());
+            }
+            camera.focal /= depth;
+            camera.model = CameraModel::Orthographic;
+        }
+    }
+
+    if let Some(out) = args.out.as_ref().filter(|_| args.sequence.is_some()) {
+        std::fs::create_dir_all(out).map_err(|e| format!("{out}: {e}"))?;
+    }
+
+    let mut pipe = args
+        .pipe
+        .as_ref()
+        .map(|command| {
+            Command::new("sh")
+                .args(["-c", command])
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("{command}: {e}"))
+        })
+        .transpose()?;
+
+    for (frame, camera) in cameras.iter().enumerate() {
+        if let Some(lod_cut) = &mut lod_cut {
+            lod_cut.update(&renderer, &queue, camera);
+        }
+        if let Some((streamer, file)) = &mut streamer {
+            stream(&queue, &renderer, streamer, file, camera)?;
+        }
+        render_frame(&device, &queue, &renderer, camera, &view);
+
+        match renderer.wait_timings(&device).await {
+            Some(timings) if args.json => println!("{}", timings.to_json()),
+            Some(timings) => println!("{timings}"),
+            None if args.profile => eprintln!("timestamp queries are not supported"),
+            None => {}
+        }
+
+        match renderer.wait_stats(&device).await {
+            Some(stats) if args.json => println!("{}", stats.to_json()),
+            Some(stats) => println!("{stats}"),
+            None => {}
+        }
+
+        if args.out.is_none() && pipe.is_none() {
+            continue;
+        }
+
+        let pixels = read_texture(&device, &queue, &texture).await;
+        let png = encode_png(width, height, &pixels)?;
+
+        if let Some(out) = &args.out {
+            let file = match args.sequence {
+                Some(_) => Path::new(out).join(format!("frame_{frame:05}.png")),
+                None => Path::new(out).to_path_buf(),
+            };
+            std::fs::write(&file, &png).map_err(|e| format!("{}: {e}", file.display()))?;
+        }
+
+        if let Some(Child {
+            stdin: Some(stdin), ..
+        }) = &mut pipe
+        {
+            stdin.write_all(&png).map_err(|e| format!("pipe: {e}"))?;
+        }
+    }
+
+    if let Some(mut child) = pipe {
+        // closing stdin ends the stream
+        drop(child.stdin.take());
+        let status = child.wait().map_err(|e| format!("pipe: {e}"))?;
+        if !status.success() {
+            return Err(format!("pipe exited with {status}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Uploads the chunks `streamer` asks for at `camera`, read from the chunk `file`.
+fn stream(
+    queue: &wgpu::Queue,
+    renderer: &Renderer,
+    streamer: &mut Streamer,
+    file: &mut File,
+    camera: &Camera,
+) -> Result<(), String> {
+    for chunk in streamer.update(renderer, queue, camera) {
+        let range = streamer.index().chunks[chunk].range();
+        let mut bytes = vec![0; (range.end - range.start) as usize];
+        file.seek(SeekFrom::Start(range.start))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|e| format!("chunk {chunk}: {e}"))?;
+        streamer.upload(renderer, queue, chunk, &chunk_gaussians(&bytes));
+    }
+
+    Ok(())
+}
+
+fn render_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &Renderer,
+    camera: &Camera,
+    view: &wgpu::TextureView,
+) {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("headless encoder"),
+    });
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+    renderer.render(
+        device,
+        queue,
+        &mut encoder,
+        camera,
+        &RenderTarget::new(view),
+    );
+    queue.submit(Some(encoder.finish()));
+}
+
+/// Encodes tightly packed RGBA8 pixels.
+fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| e.to_string())?;
+
+    Ok(png)
 }
@@ -0,0 +1,321 @@
+use std::borrow::Cow;
+
+use nalgebra::{Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    renderer::{FAR, NEAR},
+    View,
+};
+
+/// Half the size of the cross drawn on every point, in pixels.
+const MARKER_SIZE: f32 = 5.0;
+
+/// Polyline or polygon through world space points, e.g. [`crate::Pick::position`]s.
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    pub points: Vec<Point3<f32>>,
+    /// The last point connects back to the first.
+    pub closed: bool,
+}
+
+impl Measurement {
+    /// Open polyline, the distance between two points or along a path.
+    pub fn path(points: Vec<Point3<f32>>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    /// Closed polygon, for areas.
+    pub fn polygon(points: Vec<Point3<f32>>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+
+    /// Pairs of consecutive points, with the closing one of a polygon.
+    pub fn segments(&self) -> impl Iterator<Item = (&Point3<f32>, &Point3<f32>)> {
+        let closing = (self.closed && self.points.len() > 2)
+            .then(|| (self.points.last().unwrap(), &self.points[0]));
+
+        self.points
+            .iter()
+            .zip(self.points.iter().skip(1))
+            .chain(closing)
+    }
+
+    /// Sum of the segment lengths, the perimeter of a polygon.
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(a, b)| nalgebra::distance(a, b)).sum()
+    }
+
+    /// Area of the polygon through the points, projected on the plane it is closest to when
+    /// they are not coplanar. 0 for less than three points.
+    pub fn area(&self) -> f32 {
+        if self.points.len() < 3 {
+            return 0.0;
+        }
+
+        // half the norm of the vector area, relative to the first point for precision
+        let origin = self.points[0];
+        let vector_area: Vector3<f32> = self
+            .points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(a, b)| (a - origin).cross(&(b - origin)))
+            .sum();
+
+        0.5 * vector_area.norm()
+    }
+}
+
+/// `Overlay` of overlay.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OverlayUniform {
+    view_proj: [f32; 16],
+    screen: [f32; 2],
+    _pad: [f32; 2],
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for OverlayUniform {}
+unsafe impl bytemuck::Pod for OverlayUniform {}
+
+/// `Vertex` of overlay.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OverlayVertex {
+    position: [f32; 3],
+    offset: [f32; 2],
+}
+
+unsafe impl bytemuck::Zeroable for OverlayVertex {}
+unsafe impl bytemuck::Pod for OverlayVertex {}
+
+/// Draws measurements as lines with a cross on every point over a rendered view, without
+/// depth test. Fisheye and panorama views get the straight lines of a pinhole camera.
+pub(crate) struct Overlay {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("overlay bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("overlay pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("overlay shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/overlay.wgsl"))),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("overlay pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vert_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<OverlayVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "frag_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Draws `measurements` in `color`, not premultiplied, over `view` of a `width` x
+    /// `height` renderer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &View,
+        width: u32,
+        height: u32,
+        measurements: &[Measurement],
+        color: [f32; 4],
+    ) {
+        let vertices = vertices(measurements);
+        if vertices.is_empty() {
+            return;
+        }
+
+        let camera = view.camera;
+        let view_proj = camera.projection(width, height, NEAR, FAR) * camera.view;
+        let [r, g, b, a] = color;
+        let uniform = OverlayUniform {
+            view_proj: view_proj.as_slice().try_into().unwrap(),
+            screen: [width as f32, height as f32],
+            _pad: [0.0; 2],
+            color: [r * a, g * a, b * a, a],
+        };
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("overlay"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("overlay vertices"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("overlay bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("overlay"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: view.target.color,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        let [x, y] = view.origin;
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
+
+/// Line list of the segments followed by the point markers.
+fn vertices(measurements: &[Measurement]) -> Vec<OverlayVertex> {
+    let vertex = |point: &Point3<f32>, offset: [f32; 2]| OverlayVertex {
+        position: point.coords.into(),
+        offset,
+    };
+
+    let segments = measurements
+        .iter()
+        .flat_map(Measurement::segments)
+        .flat_map(|(a, b)| [vertex(a, [0.0; 2]), vertex(b, [0.0; 2])]);
+
+    let markers = measurements
+        .iter()
+        .flat_map(|measurement| &measurement.points)
+        .flat_map(|point| {
+            [
+                vertex(point, [-MARKER_SIZE, 0.0]),
+                vertex(point, [MARKER_SIZE, 0.0]),
+                vertex(point, [0.0, -MARKER_SIZE]),
+                vertex(point, [0.0, MARKER_SIZE]),
+            ]
+        });
+
+    segments.chain(markers).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(points: &[[f32; 3]]) -> Vec<Point3<f32>> {
+        points.iter().map(|&point| point.into()).collect()
+    }
+
+    fn unit_square() -> Vec<Point3<f32>> {
+        points(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ])
+    }
+
+    #[test]
+    fn length_of_path_and_polygon() {
+        let path = Measurement::path(unit_square());
+        assert_eq!(path.segments().count(), 3);
+        assert_eq!(path.length(), 3.0);
+
+        let polygon = Measurement::polygon(unit_square());
+        assert_eq!(polygon.segments().count(), 4);
+        assert_eq!(polygon.length(), 4.0);
+    }
+
+    #[test]
+    fn area_of_polygons() {
+        assert_eq!(Measurement::polygon(unit_square()).area(), 1.0);
+        assert_eq!(
+            Measurement::polygon(unit_square()[..2].to_vec()).area(),
+            0.0
+        );
+
+        // unit square tilted by 45°
+        let tilted = points(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0],
+        ]);
+        let area = Measurement::polygon(tilted).area();
+        assert!((area - 2.0_f32.sqrt()).abs() < 1e-6);
+
+        // one corner lifted, its vector area is (-1, -1, 2) / 2
+        let skew = points(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0],
+        ]);
+        let area = Measurement::polygon(skew).area();
+        assert!((area - 6.0_f32.sqrt() / 2.0).abs() < 1e-6);
+    }
+}
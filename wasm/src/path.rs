@@ -0,0 +1,283 @@
+use nalgebra::{
+    Matrix3, Point3, Rotation3, SVector, Unit, UnitQuaternion, Vector2, Vector3, Vector4,
+};
+use serde::Deserialize;
+
+use crate::{Camera, CameraModel, DatasetCamera};
+
+/// Pose and intrinsics the camera passes through.
+#[derive(Clone, Debug)]
+pub struct Keyframe {
+    pub position: Point3<f32>,
+    /// camera to world
+    pub rotation: UnitQuaternion<f32>,
+    /// vertical field of view in radians
+    pub fov: f32,
+    /// fx / fy, 1 for square pixels
+    pub pixel_aspect: f32,
+    /// principal point relative to the image size, (0.5, 0.5) at the center
+    pub principal: Vector2<f32>,
+    /// not interpolated, a segment keeps the model of its first keyframe
+    pub model: CameraModel,
+}
+
+/// Entry of a keyframes json file, oriented like `cameras.json`.
+#[derive(Deserialize)]
+struct KeyframeJson {
+    position: [f32; 3],
+    /// camera to world rotation, row major
+    rotation: [[f32; 3]; 3],
+    /// vertical field of view in degrees
+    fov: f32,
+    #[serde(default = "square_pixels")]
+    pixel_aspect: f32,
+    #[serde(default = "centered")]
+    principal: [f32; 2],
+}
+
+fn square_pixels() -> f32 {
+    1.0
+}
+
+fn centered() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+impl Keyframe {
+    /// Turned to look at `target`, `up` pointing up in the image.
+    pub fn look_at(mut self, target: &Point3<f32>, up: &Vector3<f32>) -> Self {
+        // x right, y down, z forward
+        let forward = (target - self.position).normalize();
+        let right = (-up).cross(&forward).normalize();
+        let down = forward.cross(&right);
+        let rotation =
+            Rotation3::from_matrix_unchecked(Matrix3::from_columns(&[right, down, forward]));
+
+        self.rotation = UnitQuaternion::from_rotation_matrix(&rotation);
+        self
+    }
+
+    /// Camera of a `width` x `height` image.
+    pub fn camera(&self, width: u32, height: u32) -> Camera {
+        let focal = height as f32 / (2.0 * (self.fov * 0.5).tan());
+        let size = Vector2::new(width as f32, height as f32);
+
+        Camera::from_pose(
+            self.rotation.to_rotation_matrix().matrix(),
+            &self.position,
+            Vector2::new(focal * self.pixel_aspect, focal),
+            self.principal.component_mul(&size),
+        )
+        .with_model(self.model)
+    }
+}
+
+impl From<&DatasetCamera> for Keyframe {
+    fn from(camera: &DatasetCamera) -> Self {
+        let rotation = Matrix3::from_fn(|i, j| camera.rotation[i][j]);
+
+        Self {
+            position: Point3::from(camera.position),
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix(&rotation)),
+            fov: 2.0 * f32::atan(camera.height as f32 / (2.0 * camera.fy)),
+            pixel_aspect: camera.fx / camera.fy,
+            principal: camera
+                .principal()
+                .component_div(&Vector2::new(camera.width as f32, camera.height as f32)),
+            model: camera.model(),
+        }
+    }
+}
+
+impl From<KeyframeJson> for Keyframe {
+    fn from(json: KeyframeJson) -> Self {
+        let rotation = Matrix3::from_fn(|i, j| json.rotation[i][j]);
+
+        Self {
+            position: Point3::from(json.position),
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix(&rotation)),
+            fov: json.fov.to_radians(),
+            pixel_aspect: json.pixel_aspect,
+            principal: Vector2::from(json.principal),
+            model: CameraModel::Pinhole,
+        }
+    }
+}
+
+/// Smooth path through keyframes, Catmull-Rom for positions and field of view, slerp for
+/// rotations. Keyframes are evenly spaced in time.
+#[derive(Clone, Debug)]
+pub struct CameraPath {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Fails without a keyframe.
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self, String> {
+        if keyframes.is_empty() {
+            return Err("a camera path needs a keyframe".to_string());
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// Path through the cameras of a dataset in their order, interpolating their intrinsics.
+    pub fn through(cameras: &[DatasetCamera]) -> Result<Self, String> {
+        Self::new(cameras.iter().map(Keyframe::from).collect())
+    }
+
+    /// Parses `[{"position": [x, y, z], "rotation": [[...], [...], [...]], "fov": 50}, ...]`.
+    pub fn load(json: &str) -> Result<Self, String> {
+        let keyframes: Vec<KeyframeJson> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Self::new(keyframes.into_iter().map(Keyframe::from).collect())
+    }
+
+    /// Keyframe at `t` from 0 at the first keyframe to 1 at the last.
+    pub fn sample(&self, t: f32) -> Keyframe {
+        let last = self.keyframes.len() - 1;
+        if last == 0 {
+            return self.keyframes[0].clone();
+        }
+
+        let s = t.clamp(0.0, 1.0) * last as f32;
+        let i = (s.floor() as usize).min(last - 1);
+        let u = s - i as f32;
+
+        // neighbours of the segment, repeated at the ends
+        let k0 = &self.keyframes[i.saturating_sub(1)];
+        let k1 = &self.keyframes[i];
+        let k2 = &self.keyframes[i + 1];
+        let k3 = &self.keyframes[(i + 2).min(last)];
+
+        let position = catmull_rom(
+            k0.position.coords,
+            k1.position.coords,
+            k2.position.coords,
+            k3.position.coords,
+            u,
+        );
+        let intrinsics =
+            |k: &Keyframe| Vector4::new(k.fov, k.pixel_aspect, k.principal.x, k.principal.y);
+        let intrinsics = catmull_rom(
+            intrinsics(k0),
+            intrinsics(k1),
+            intrinsics(k2),
+            intrinsics(k3),
+            u,
+        );
+
+        // shortest arc
+        let end = if k1.rotation.coords.dot(&k2.rotation.coords) < 0.0 {
+            UnitQuaternion::new_unchecked(-k2.rotation.into_inner())
+        } else {
+            k2.rotation
+        };
+
+        Keyframe {
+            position: Point3::from(position),
+            rotation: k1.rotation.slerp(&end, u),
+            fov: intrinsics.x,
+            pixel_aspect: intrinsics.y,
+            principal: Vector2::new(intrinsics.z, intrinsics.w),
+            model: k1.model,
+        }
+    }
+
+    /// `num_frame` cameras evenly spread over the path, both ends included.
+    pub fn cameras(&self, num_frame: usize, width: u32, height: u32) -> Vec<Camera> {
+        (0..num_frame)
+            .map(|frame| {
+                let t = frame as f32 / (num_frame.max(2) - 1) as f32;
+                self.sample(t).camera(width, height)
+            })
+            .collect()
+    }
+}
+
+/// `num_frame` keyframes on a full turn of `start` around the `up` axis through `center`,
+/// looking at `center`. The last frame stops one step short of `start`, so the orbit loops.
+pub fn orbit(
+    start: &Keyframe,
+    center: &Point3<f32>,
+    up: &Unit<Vector3<f32>>,
+    num_frame: usize,
+) -> Vec<Keyframe> {
+    (0..num_frame)
+        .map(|frame| {
+            let angle = std::f32::consts::TAU * frame as f32 / num_frame as f32;
+            let turn = UnitQuaternion::from_axis_angle(up, angle);
+            let position = center + turn * (start.position - center);
+
+            Keyframe {
+                position,
+                ..start.clone()
+            }
+            .look_at(center, up)
+        })
+        .collect()
+}
+
+/// Uniform Catmull-Rom spline between `p1` and `p2`.
+fn catmull_rom<const D: usize>(
+    p0: SVector<f32, D>,
+    p1: SVector<f32, D>,
+    p2: SVector<f32, D>,
+    p3: SVector<f32, D>,
+    u: f32,
+) -> SVector<f32, D> {
+    let u2 = u * u;
+    let u3 = u2 * u;
+
+    0.5 * (2.0 * p1
+        + (p2 - p0) * u
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"[
+        {"position": [0, 0, 0], "rotation": [[1, 0, 0], [0, 1, 0], [0, 0, 1]], "fov": 40},
+        {"position": [1, 0, 0], "rotation": [[0, 0, 1], [0, 1, 0], [-1, 0, 0]], "fov": 50},
+        {"position": [2, 1, 0], "rotation": [[-1, 0, 0], [0, 1, 0], [0, 0, -1]], "fov": 60,
+         "principal": [0.4, 0.6]}
+    ]"#;
+
+    fn assert_keyframe_eq(a: &Keyframe, b: &Keyframe) {
+        assert!((a.position - b.position).norm() < 1e-5);
+        assert!(a.rotation.angle_to(&b.rotation) < 1e-3);
+        assert!((a.fov - b.fov).abs() < 1e-5);
+        assert!((a.pixel_aspect - b.pixel_aspect).abs() < 1e-5);
+        assert!((a.principal - b.principal).norm() < 1e-5);
+    }
+
+    #[test]
+    fn sample_passes_through_keyframes() {
+        let path = CameraPath::load(JSON).unwrap();
+        assert_keyframe_eq(&path.sample(0.0), &path.keyframes[0]);
+        assert_keyframe_eq(&path.sample(0.5), &path.keyframes[1]);
+        assert_keyframe_eq(&path.sample(1.0), &path.keyframes[2]);
+        // clamped outside of the path
+        assert_keyframe_eq(&path.sample(2.0), &path.keyframes[2]);
+
+        let single = CameraPath::new(path.keyframes[..1].to_vec()).unwrap();
+        assert_keyframe_eq(&single.sample(0.7), &path.keyframes[0]);
+    }
+
+    #[test]
+    fn empty_path_is_rejected() {
+        assert!(CameraPath::new(Vec::new()).is_err());
+        assert!(CameraPath::load("[]").is_err());
+        assert!(CameraPath::through(&[]).is_err());
+    }
+
+    #[test]
+    fn catmull_rom_interpolates() {
+        let p = |x: f32| Vector3::new(x, 2.0 * x, 0.0);
+        assert_eq!(catmull_rom(p(0.0), p(1.0), p(2.0), p(3.0), 0.0), p(1.0));
+        assert_eq!(catmull_rom(p(0.0), p(1.0), p(2.0), p(3.0), 1.0), p(2.0));
+        // evenly spaced points give a straight line at constant speed
+        assert_eq!(catmull_rom(p(0.0), p(1.0), p(2.0), p(3.0), 0.25), p(1.25));
+    }
+}
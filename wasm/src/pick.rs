@@ -0,0 +1,212 @@
+use nalgebra::{Point3, Vector2};
+use wasm_bindgen::prelude::*;
+
+use crate::renderer::read_buffer;
+
+/// `NO_SPLAT` of rasterize.wgsl
+const NO_SPLAT: u32 = u32::MAX;
+
+/// Bytes of the `Pick` struct of rasterize.wgsl.
+const PICK_SIZE: u64 = 32;
+
+/// What is seen through a pixel, see [`crate::Renderer::pick`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Pick {
+    /// Object of the dominant Gaussian.
+    pub object: usize,
+    /// Dominant Gaussian, the one with the largest blending weight, counted from the first
+    /// Gaussian of its object.
+    pub gaussian: u64,
+    /// Blending weight of the dominant Gaussian.
+    pub weight: f32,
+    /// Accumulated over every Gaussian in front of the pixel.
+    pub opacity: f32,
+    /// Expected depth, along the optical axis for pinhole and orthographic cameras and the
+    /// distance to the camera for the others.
+    pub depth: f32,
+    /// World space point at the expected depth.
+    #[wasm_bindgen(skip)]
+    pub position: Point3<f32>,
+}
+
+#[wasm_bindgen]
+impl Pick {
+    /// World space point at the expected depth, x, y and z.
+    #[wasm_bindgen(getter, js_name = position)]
+    pub fn position_js(&self) -> Vec<f32> {
+        self.position.coords.as_slice().to_vec()
+    }
+}
+
+/// `Pick` of rasterize.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PickUniform {
+    splat: u32,
+    weight: f32,
+    opacity: f32,
+    depth: f32,
+    position: [f32; 3],
+    _pad: f32,
+}
+
+unsafe impl bytemuck::Zeroable for PickUniform {}
+unsafe impl bytemuck::Pod for PickUniform {}
+
+/// Variant of the rasterizer blending a single pixel, keeping the splat with the largest
+/// weight instead of a color.
+pub(crate) struct Picker {
+    pipeline: wgpu::ComputePipeline,
+    pixel_buffer: wgpu::Buffer,
+    pick_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Picker {
+    /// `module` is rasterize.wgsl and `layouts` the bind group layouts of its pipeline.
+    pub fn new(
+        device: &wgpu::Device,
+        module: &wgpu::ShaderModule,
+        layouts: &[&wgpu::BindGroupLayout; 3],
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("pick bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pick pipeline layout"),
+            bind_group_layouts: &[layouts[0], layouts[1], layouts[2], &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("pick pipeline"),
+            layout: Some(&pipeline_layout),
+            module,
+            entry_point: "pick",
+        });
+
+        let pixel_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick pixel"),
+            size: 8,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pick_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick"),
+            size: PICK_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick staging buffer"),
+            size: PICK_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("pick bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: pixel_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: pick_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            pipeline,
+            pixel_buffer,
+            pick_buffer,
+            staging_buffer,
+            bind_group,
+        }
+    }
+
+    /// Blends `pixel` with the splats sorted into the tiles by the sort recorded before it in
+    /// `encoder`. `bind_groups` are the ones of the rasterize pass of that sort.
+    pub fn record(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_groups: &[&wgpu::BindGroup; 3],
+        pixel: Vector2<u32>,
+    ) {
+        queue.write_buffer(
+            &self.pixel_buffer,
+            0,
+            bytemuck::cast_slice(pixel.as_slice()),
+        );
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("pick"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                pass.set_bind_group(index as u32, bind_group, &[]);
+            }
+            pass.set_bind_group(3, &self.bind_group, &[]);
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&self.pick_buffer, 0, &self.staging_buffer, 0, PICK_SIZE);
+    }
+
+    /// Gaussian under the pixel and the expected surface there, `None` when nothing is seen.
+    /// `locate` finds the object and Gaussian of a splat. Must be called after the recorded
+    /// pick is submitted.
+    pub async fn read(
+        &self,
+        device: &wgpu::Device,
+        locate: impl Fn(u64) -> (usize, u64),
+    ) -> Option<Pick> {
+        let bytes = read_buffer(device, &self.staging_buffer).await;
+        let pick: PickUniform = *bytemuck::from_bytes(&bytes);
+
+        if pick.splat == NO_SPLAT {
+            return None;
+        }
+
+        let (object, gaussian) = locate(pick.splat as u64);
+        Some(Pick {
+            object,
+            gaussian,
+            weight: pick.weight,
+            opacity: pick.opacity,
+            depth: pick.depth,
+            position: Point3::from(pick.position),
+        })
+    }
+}
@@ -0,0 +1,217 @@
+//! Native port of `app/src/ply.ts` and the `preprocess` of `app/src/util.ts`.
+
+/// Floats per Gaussian in the `gaussians` storage buffer of `preprocess.wgsl`.
+pub const GAUSSIAN_FLOATS: usize = 80;
+
+// offsets of the fields of the wgsl `Gaussian` struct, in floats
+pub(crate) const MEAN: usize = 0;
+pub(crate) const NORM: usize = 4;
+pub(crate) const FILTER_3D: usize = 7;
+pub(crate) const SH: usize = 8;
+pub(crate) const SCALE: usize = 72;
+pub(crate) const OPACITY: usize = 75;
+pub(crate) const ROTATION: usize = 76;
+
+/// Gaussians of a 3D Gaussian Splatting `point_cloud.ply`, laid out for [`crate::Renderer`].
+pub struct PointCloud {
+    pub gaussians: Vec<f32>,
+    pub num_gaussian: u64,
+    /// The file has the `filter_3D` property of Mip-Splatting.
+    pub mip_splatting: bool,
+}
+
+struct Property {
+    name: String,
+    ty: String,
+}
+
+impl Property {
+    fn size(&self) -> Result<usize, String> {
+        match self.ty.as_str() {
+            "char" | "int8" | "uchar" | "uint8" => Ok(1),
+            "short" | "int16" | "ushort" | "uint16" => Ok(2),
+            "int" | "int32" | "uint" | "uint32" | "float" | "float32" => Ok(4),
+            "double" | "float64" => Ok(8),
+            ty => Err(format!("unknown property type {ty}")),
+        }
+    }
+
+    fn read(&self, bytes: &[u8], little_endian: bool) -> f32 {
+        macro_rules! read {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().unwrap();
+                if little_endian {
+                    <$t>::from_le_bytes(bytes) as f32
+                } else {
+                    <$t>::from_be_bytes(bytes) as f32
+                }
+            }};
+        }
+
+        match self.ty.as_str() {
+            "char" | "int8" => read!(i8),
+            "uchar" | "uint8" => read!(u8),
+            "short" | "int16" => read!(i16),
+            "ushort" | "uint16" => read!(u16),
+            "int" | "int32" => read!(i32),
+            "uint" | "uint32" => read!(u32),
+            "float" | "float32" => read!(f32),
+            _ => read!(f64),
+        }
+    }
+}
+
+/// Parses a binary PLY and activates scales, opacities and rotations as the web app does.
+pub fn load(bytes: &[u8]) -> Result<PointCloud, String> {
+    let header_end = bytes
+        .windows(11)
+        .position(|window| window == b"end_header\n")
+        .ok_or("missing end_header")?;
+    let header = std::str::from_utf8(&bytes[..header_end]).map_err(|e| e.to_string())?;
+
+    let mut little_endian = true;
+    let mut num_gaussian = 0;
+    let mut properties = Vec::new();
+    for line in header.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, ..] => match *format {
+                "binary_little_endian" => little_endian = true,
+                "binary_big_endian" => little_endian = false,
+                format => return Err(format!("unsupported format {format}")),
+            },
+            ["element", "vertex", count] => {
+                num_gaussian = count.parse().map_err(|_| "invalid vertex count")?
+            }
+            ["property", ty, name] => properties.push(Property {
+                name: name.to_string(),
+                ty: ty.to_string(),
+            }),
+            _ => {}
+        }
+    }
+
+    let stride = properties
+        .iter()
+        .map(Property::size)
+        .sum::<Result<usize, String>>()?;
+    let body = &bytes[header_end + 11..];
+    if body.len() < stride * num_gaussian {
+        return Err(format!("expected {num_gaussian} vertices"));
+    }
+
+    let mut gaussians = vec![0.0; num_gaussian * GAUSSIAN_FLOATS];
+    for (vertex, gaussian) in body
+        .chunks_exact(stride)
+        .zip(gaussians.chunks_exact_mut(GAUSSIAN_FLOATS))
+    {
+        let mut offset = 0;
+        for property in &properties {
+            let size = property.size()?;
+            let value = property.read(&vertex[offset..offset + size], little_endian);
+            offset += size;
+
+            if let Some(index) = field(&property.name) {
+                gaussian[index] = value;
+            }
+        }
+        activate(gaussian);
+    }
+
+    Ok(PointCloud {
+        gaussians,
+        num_gaussian: num_gaussian as u64,
+        mip_splatting: properties.iter().any(|p| p.name == "filter_3D"),
+    })
+}
+
+/// Binary PLY of packed `Gaussian` structs, e.g. from [`crate::Renderer::read_gaussians`], with
+/// the properties [`load`] reads. `filter_3D` is written for Mip-Splatting models.
+pub fn save(gaussians: &[f32], mip_splatting: bool) -> Vec<u8> {
+    let mut names: Vec<String> = ["x", "y", "z", "nx", "ny", "nz"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    names.extend((0..3).map(|i| format!("f_dc_{i}")));
+    names.extend((0..45).map(|i| format!("f_rest_{i}")));
+    names.push("opacity".to_string());
+    names.extend((0..3).map(|i| format!("scale_{i}")));
+    names.extend((0..4).map(|i| format!("rot_{i}")));
+    if mip_splatting {
+        names.push("filter_3D".to_string());
+    }
+
+    let num_gaussian = gaussians.len() / GAUSSIAN_FLOATS;
+    let mut bytes =
+        format!("ply\nformat binary_little_endian 1.0\nelement vertex {num_gaussian}\n")
+            .into_bytes();
+    for name in &names {
+        bytes.extend(format!("property float {name}\n").as_bytes());
+    }
+    bytes.extend(b"end_header\n");
+
+    let fields: Vec<usize> = names.iter().filter_map(|name| field(name)).collect();
+    let mut gaussian = [0.0; GAUSSIAN_FLOATS];
+    for activated in gaussians.chunks_exact(GAUSSIAN_FLOATS) {
+        gaussian.copy_from_slice(activated);
+        deactivate(&mut gaussian);
+
+        for index in &fields {
+            bytes.extend(gaussian[*index].to_le_bytes());
+        }
+    }
+
+    bytes
+}
+
+/// Float of the `Gaussian` struct a PLY property is stored in.
+fn field(name: &str) -> Option<usize> {
+    let index = |prefix: &str| name.strip_prefix(prefix)?.parse::<usize>().ok();
+
+    match name {
+        "x" => Some(MEAN),
+        "y" => Some(MEAN + 1),
+        "z" => Some(MEAN + 2),
+        "nx" => Some(NORM),
+        "ny" => Some(NORM + 1),
+        "nz" => Some(NORM + 2),
+        "filter_3D" => Some(FILTER_3D),
+        "opacity" => Some(OPACITY),
+        _ => {
+            if let Some(i) = index("f_dc_").filter(|i| *i < 3) {
+                Some(SH + i)
+            } else if let Some(i) = index("f_rest_").filter(|i| *i < 45) {
+                // sh[1 + i / 3][i % 3], every vec3f padded to 4 floats
+                Some(SH + 4 * (1 + i / 3) + i % 3)
+            } else if let Some(i) = index("scale_").filter(|i| *i < 3) {
+                Some(SCALE + i)
+            } else {
+                index("rot_").filter(|i| *i < 4).map(|i| ROTATION + i)
+            }
+        }
+    }
+}
+
+fn activate(gaussian: &mut [f32]) {
+    for scale in &mut gaussian[SCALE..SCALE + 3] {
+        *scale = scale.exp();
+    }
+
+    gaussian[OPACITY] = 1.0 / (1.0 + (-gaussian[OPACITY]).exp());
+
+    let rotation = &mut gaussian[ROTATION..ROTATION + 4];
+    let norm = rotation.iter().map(|r| r * r).sum::<f32>().sqrt();
+    for r in rotation {
+        *r /= norm;
+    }
+}
+
+/// Inverse of [`activate`], rotations stay normalized.
+fn deactivate(gaussian: &mut [f32]) {
+    for scale in &mut gaussian[SCALE..SCALE + 3] {
+        *scale = scale.ln();
+    }
+
+    let opacity = gaussian[OPACITY].clamp(1e-6, 1.0 - 1e-6);
+    gaussian[OPACITY] = (opacity / (1.0 - opacity)).ln();
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use crate::readback::Readback;
+
+/// Passes of [`crate::Renderer::render`] with their own timestamps, in recording order.
+/// The sort is recorded by wgpu_sort in passes we do not own, it is timed as the gap between
+/// the key copy and the range pass.
+const PASSES: [&str; 7] = [
+    "preprocess",
+    "prefix sum",
+    "finish prefix sum",
+    "key copy",
+    "range",
+    "rasterize",
+    "present",
+];
+
+const PASS_KEY_COPY: usize = 3;
+const PASS_RANGE: usize = 4;
+
+const NUM_QUERY: u32 = PASSES.len() as u32 * 2;
+
+/// GPU timestamps around every stage of a frame.
+pub struct Profiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Readback,
+    /// nanoseconds per timestamp tick
+    period: f32,
+}
+
+impl Profiler {
+    /// `None` when the device was created without [`wgpu::Features::TIMESTAMP_QUERY`].
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("profiler queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: NUM_QUERY,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("profiler resolve buffer"),
+            size: NUM_QUERY as u64 * 8,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = Readback::new(device, "profiler staging buffer", NUM_QUERY as u64 * 8);
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback,
+            period: queue.get_timestamp_period(),
+        })
+    }
+
+    pub(crate) fn compute_pass(&self, pass: usize) -> wgpu::ComputePassTimestampWrites<'_> {
+        wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(pass as u32 * 2),
+            end_of_pass_write_index: Some(pass as u32 * 2 + 1),
+        }
+    }
+
+    pub(crate) fn render_pass(&self, pass: usize) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(pass as u32 * 2),
+            end_of_pass_write_index: Some(pass as u32 * 2 + 1),
+        }
+    }
+
+    /// Copies the timestamps of the frame out, after all its passes. Skipped while the
+    /// timestamps of the two frames before are still being read.
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..NUM_QUERY, &self.resolve_buffer, 0);
+        self.readback.record(encoder, &self.resolve_buffer);
+    }
+
+    /// Timings of the newest submitted frame whose timestamps are mapped, without waiting
+    /// for the GPU. `None` until then, call it every frame.
+    pub fn read(&self, device: &wgpu::Device) -> Option<StageTimings> {
+        Some(self.timings(&self.readback.poll(device)?))
+    }
+
+    /// Waits for the timings of the last submitted frame, `None` if they were already read.
+    pub async fn wait(&self, device: &wgpu::Device) -> Option<StageTimings> {
+        Some(self.timings(&self.readback.wait(device).await?))
+    }
+
+    fn timings(&self, bytes: &[u8]) -> StageTimings {
+        let ticks: &[u64] = bytemuck::cast_slice(bytes);
+
+        let millis = |begin: u64, end: u64| {
+            end.saturating_sub(begin) as f64 * self.period as f64 / 1_000_000.0
+        };
+        let pass = |i: usize| millis(ticks[i * 2], ticks[i * 2 + 1]);
+
+        let mut stages: Vec<(&'static str, f64)> = PASSES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, pass(i)))
+            .collect();
+        stages.insert(
+            PASS_RANGE,
+            (
+                "sort",
+                millis(ticks[PASS_KEY_COPY * 2 + 1], ticks[PASS_RANGE * 2]),
+            ),
+        );
+
+        StageTimings { stages }
+    }
+}
+
+/// Milliseconds spent in every stage of a frame.
+#[derive(Clone, Debug)]
+pub struct StageTimings {
+    pub stages: Vec<(&'static str, f64)>,
+}
+
+impl StageTimings {
+    pub fn total(&self) -> f64 {
+        self.stages.iter().map(|(_, ms)| ms).sum()
+    }
+
+    /// `{"preprocess": 0.12, ..., "total": 3.4}`
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .stages
+            .iter()
+            .map(|(name, ms)| format!("\"{name}\": {ms:.4}"))
+            .chain(std::iter::once(format!("\"total\": {:.4}", self.total())))
+            .collect();
+
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, ms) in &self.stages {
+            writeln!(f, "{name:>18}: {ms:8.3} ms")?;
+        }
+        write!(f, "{:>18}: {:8.3} ms", "total", self.total())
+    }
+}
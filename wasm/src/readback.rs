@@ -0,0 +1,121 @@
+use std::sync::{
+    atomic::{AtomicU8, AtomicUsize, Ordering},
+    Arc,
+};
+
+const IDLE: u8 = 0;
+const RECORDED: u8 = 1;
+const MAPPING: u8 = 2;
+const MAPPED: u8 = 3;
+
+/// Copies a buffer into one of two staging buffers every frame, so frames can be recorded
+/// while the copy of a previous one is still being mapped.
+pub(crate) struct Readback {
+    staging_buffers: [wgpu::Buffer; 2],
+    states: [Arc<AtomicU8>; 2],
+    /// staging buffer of the last recorded frame
+    latest: AtomicUsize,
+}
+
+impl Readback {
+    pub fn new(device: &wgpu::Device, label: &str, size: u64) -> Self {
+        let staging_buffer = || {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            staging_buffers: [staging_buffer(), staging_buffer()],
+            states: [Arc::new(AtomicU8::new(IDLE)), Arc::new(AtomicU8::new(IDLE))],
+            latest: AtomicUsize::new(0),
+        }
+    }
+
+    /// Copies the start of `source` out, skipped while both staging buffers are busy.
+    pub fn record(&self, encoder: &mut wgpu::CommandEncoder, source: &wgpu::Buffer) {
+        let Some(index) = (0..2).find(|i| {
+            self.states[*i]
+                .compare_exchange(IDLE, RECORDED, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        }) else {
+            return;
+        };
+
+        // a recorded but never mapped frame is superseded
+        let previous = self.latest.swap(index, Ordering::AcqRel);
+        if previous != index {
+            let _ = self.states[previous].compare_exchange(
+                RECORDED,
+                IDLE,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+        }
+
+        let staging_buffer = &self.staging_buffers[index];
+        encoder.copy_buffer_to_buffer(source, 0, staging_buffer, 0, staging_buffer.size());
+    }
+
+    /// Starts mapping the copy of the last submitted frame and returns the newest mapped one
+    /// without waiting, `None` until one is mapped. Meant to be called once per frame.
+    pub fn poll(&self, device: &wgpu::Device) -> Option<Vec<u8>> {
+        self.map_latest();
+        device.poll(wgpu::Maintain::Poll);
+        self.take_mapped()
+    }
+
+    /// Waits for the copy of the last submitted frame, `None` if it was already read.
+    pub async fn wait(&self, device: &wgpu::Device) -> Option<Vec<u8>> {
+        if let Some(receiver) = self.map_latest() {
+            device.poll(wgpu::Maintain::Wait);
+            let _ = receiver.await;
+        }
+        self.take_mapped()
+    }
+
+    /// Maps the last recorded copy, which must have been submitted. Resolves once mapped.
+    fn map_latest(&self) -> Option<futures_channel::oneshot::Receiver<()>> {
+        let index = self.latest.load(Ordering::Acquire);
+        self.states[index]
+            .compare_exchange(RECORDED, MAPPING, Ordering::AcqRel, Ordering::Acquire)
+            .ok()?;
+
+        let state = self.states[index].clone();
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.staging_buffers[index]
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                state.store(
+                    if result.is_ok() { MAPPED } else { IDLE },
+                    Ordering::Release,
+                );
+                let _ = sender.send(());
+            });
+
+        Some(receiver)
+    }
+
+    /// Content of the newest mapped staging buffer, an older one mapped too is dropped.
+    fn take_mapped(&self) -> Option<Vec<u8>> {
+        let latest = self.latest.load(Ordering::Acquire);
+        let mut bytes = None;
+        for index in [latest, 1 - latest] {
+            if self.states[index].load(Ordering::Acquire) != MAPPED {
+                continue;
+            }
+
+            let staging_buffer = &self.staging_buffers[index];
+            if bytes.is_none() {
+                bytes = Some(staging_buffer.slice(..).get_mapped_range().to_vec());
+            }
+            staging_buffer.unmap();
+            self.states[index].store(IDLE, Ordering::Release);
+        }
+
+        bytes
+    }
+}
@@ -0,0 +1,2392 @@
+use std::{borrow::Cow, num::NonZeroU32, ops::Range};
+
+use nalgebra::{Matrix4, Vector2, Vector3};
+use wgpu::{
+    util::DeviceExt, BindGroupLayoutEntry, BindingType, ComputePipelineDescriptor, FragmentState,
+    MultisampleState, PipelineLayoutDescriptor, PrimitiveState, RenderPipelineDescriptor,
+    ShaderStages, VertexState,
+};
+use wgpu_sort::{utils::guess_workgroup_size, GPUSorter, SortBuffers};
+
+use crate::{
+    camera::Camera,
+    crop::{Crop, CropUniform},
+    edit::{EditBuffers, Editor, Op, SelectMode, Selector, STATE_DELETED, STATE_SIZE},
+    lod::{LodTree, LodUniform, LOD_SIZE},
+    measure::{Measurement, Overlay},
+    pick::{Pick, Picker},
+    ply::GAUSSIAN_FLOATS,
+    profiler::{Profiler, StageTimings},
+    readback::Readback,
+    scene::{ObjectUniform, SceneObject},
+    settings::{Background, RenderMode, RenderSettings, SettingsUniform},
+    stats::{FrameStats, STATS_HEADER_SIZE, STATS_KEYS_OFFSET},
+    transform::Transform,
+};
+
+const SPLAT_SIZE: u64 = 64;
+const GAUSSIAN_SIZE: u64 = GAUSSIAN_FLOATS as u64 * 4;
+/// GPU memory of a Gaussian with its state, LOD node, splat and prefix sum.
+pub(crate) const BYTES_PER_GAUSSIAN: u64 = GAUSSIAN_SIZE + STATE_SIZE + LOD_SIZE + SPLAT_SIZE + 4;
+const NUM_SLPAT: u32 = 130000000;
+// const NUM_SLPAT: u32 = 600000 * 20;
+
+const WG_SIZE: u64 = 64;
+
+/// Workgroups per dimension of a dispatch, `max_compute_workgroups_per_dimension` of
+/// [`Renderer::required_limits`].
+const MAX_WORKGROUPS: u64 = 65535;
+
+const TILE_SZ: u32 = 8;
+
+pub(crate) const NEAR: f32 = 0.01;
+pub(crate) const FAR: f32 = 1000.0;
+
+/// Views [`Renderer::render_views`] can draw in one frame, two eyes of a headset.
+pub const MAX_VIEWS: usize = 2;
+
+const CHANNEL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// Depth buffer of opaque geometry the splats are composited with.
+#[derive(Clone, Copy)]
+pub struct DepthTarget<'a> {
+    /// Single sampled depth view of the target size, with `TEXTURE_BINDING` usage.
+    pub view: &'a wgpu::TextureView,
+    /// View to clip transform the depth was written with, clip z in [0, 1].
+    pub projection: Matrix4<f32>,
+}
+
+/// Where [`Renderer::render`] composites the splats.
+#[derive(Clone, Copy)]
+pub struct RenderTarget<'a> {
+    pub color: &'a wgpu::TextureView,
+    /// Stop blending splats behind the opaque surface of every pixel. The renderer's
+    /// background is left out, the host draws its own.
+    pub depth: Option<DepthTarget<'a>>,
+}
+
+impl<'a> RenderTarget<'a> {
+    pub fn new(color: &'a wgpu::TextureView) -> Self {
+        Self { color, depth: None }
+    }
+
+    pub fn with_depth(mut self, view: &'a wgpu::TextureView, projection: Matrix4<f32>) -> Self {
+        self.depth = Some(DepthTarget { view, projection });
+        self
+    }
+}
+
+/// One view of a multiview frame, an eye of a headset.
+#[derive(Clone, Copy)]
+pub struct View<'a> {
+    pub camera: &'a Camera,
+    pub target: RenderTarget<'a>,
+    /// Top left pixel of the view in its target, the view covers the renderer's size from there.
+    /// The depth target is addressed the same way.
+    pub origin: [u32; 2],
+}
+
+/// Uniforms differing between the views of a frame. Writes to one buffer all land before
+/// the frame runs, so every view recorded into an encoder needs its own.
+struct ViewBuffers {
+    view_matrix: wgpu::Buffer,
+    proj_matrix: wgpu::Buffer,
+    focal: wgpu::Buffer,
+    tan_fov: wgpu::Buffer,
+    principal: wgpu::Buffer,
+    projection: wgpu::Buffer,
+    screen: wgpu::Buffer,
+    camera: wgpu::Buffer,
+    origin: wgpu::Buffer,
+    depth_test: wgpu::Buffer,
+}
+
+impl ViewBuffers {
+    fn new(device: &wgpu::Device) -> Self {
+        let uniform_buffer = |label: &str, size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            view_matrix: uniform_buffer("viewMat", 64),
+            proj_matrix: uniform_buffer("projection", 64),
+            focal: uniform_buffer("focal", 8),
+            tan_fov: uniform_buffer("tan_fov", 8),
+            principal: uniform_buffer("principal", 8),
+            projection: uniform_buffer(
+                "projection model",
+                std::mem::size_of::<ProjectionUniform>() as u64,
+            ),
+            screen: uniform_buffer("screen", 8),
+            camera: uniform_buffer("Camera", 12),
+            origin: uniform_buffer("origin", 8),
+            depth_test: uniform_buffer(
+                "depth test",
+                std::mem::size_of::<DepthTestUniform>() as u64,
+            ),
+        }
+    }
+}
+
+/// `DepthTest` uniform of rasterize.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DepthTestUniform {
+    projection: [f32; 4],
+    enabled: u32,
+    _pad: [u32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for DepthTestUniform {}
+unsafe impl bytemuck::Pod for DepthTestUniform {}
+
+/// `Projection` uniform of preprocess.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ProjectionUniform {
+    model: u32,
+    _pad: [u32; 3],
+    distortion: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for ProjectionUniform {}
+unsafe impl bytemuck::Pod for ProjectionUniform {}
+
+/// Gaussian splatting renderer drawing into textures of a device owned by the caller.
+///
+/// All passes are recorded into the caller's command encoder, the splats are composited
+/// over the content of the target with premultiplied alpha.
+pub struct Renderer {
+    /// Gaussians of all objects
+    num_gaussian: u64,
+    /// `STATE_*` bits of edit.wgsl for every Gaussian
+    state_buffer: wgpu::Buffer,
+    /// `Lod` of preprocess.wgsl for every Gaussian
+    lod_buffer: wgpu::Buffer,
+    editor: Editor,
+    picker: Picker,
+    overlay: Overlay,
+    /// one for every Gaussian of every object
+    num_splat: u64,
+    width: u32,
+    height: u32,
+    settings: RenderSettings,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    sort_bind_group_layout: wgpu::BindGroupLayout,
+    tile_bind_group_layout: wgpu::BindGroupLayout,
+    depth_bind_group_layout: wgpu::BindGroupLayout,
+    stats_bind_group_layout: wgpu::BindGroupLayout,
+    object_bind_group_layout: wgpu::BindGroupLayout,
+
+    preprocess_pipeline: wgpu::ComputePipeline,
+    prefix_sum_pipeline: wgpu::ComputePipeline,
+    finish_prefix_sum_pipeline: wgpu::ComputePipeline,
+    copy_pair_pipeline: wgpu::ComputePipeline,
+    range_pipeline: wgpu::ComputePipeline,
+    rasterize_pipeline: wgpu::ComputePipeline,
+    /// heatmaps and outlines of the debug render modes
+    debug_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    count_visible_pipeline: wgpu::ComputePipeline,
+    tile_stats_pipeline: wgpu::ComputePipeline,
+
+    sorter: GPUSorter,
+    sort_buffers: SortBuffers,
+
+    gaussian_buffer: wgpu::Buffer,
+    splat_buffer: wgpu::Buffer,
+    views: Vec<ViewBuffers>,
+    output_buffer: wgpu::Buffer,
+    settings_buffer: wgpu::Buffer,
+    /// scene to world
+    transform: Transform,
+    objects: Vec<ObjectSlot>,
+    /// `Object` of preprocess.wgsl for every object
+    object_buffer: wgpu::Buffer,
+    crops: Vec<Crop>,
+    /// `Crop` of preprocess.wgsl for every crop volume, `CropUniform::NONE` without any
+    crop_buffer: wgpu::Buffer,
+    channel_texture: wgpu::Texture,
+    environment_texture: wgpu::Texture,
+    environment_sampler: wgpu::Sampler,
+
+    sort_size_buffer: wgpu::Buffer,
+    prefix_sum_buffer: wgpu::Buffer,
+    range_buffer: wgpu::Buffer,
+    sort_dispatch_buffer: wgpu::Buffer,
+    range_dispatch_buffer: wgpu::Buffer,
+
+    profiler: Option<Profiler>,
+
+    /// `FrameStats` of stats.wgsl, followed by the saturated pixels of every tile
+    stats_buffer: wgpu::Buffer,
+    stats_readback: Option<Readback>,
+
+    /// bound when the target has no depth buffer
+    no_depth_bind_group: wgpu::BindGroup,
+
+    /// one for every view
+    bind_groups: Vec<wgpu::BindGroup>,
+    sort_bind_group: wgpu::BindGroup,
+    tile_bind_group: wgpu::BindGroup,
+    stats_bind_group: wgpu::BindGroup,
+    object_bind_group: wgpu::BindGroup,
+}
+
+/// Gaussians of an object in the shared buffer, instances of an asset share them.
+struct ObjectSlot {
+    object: SceneObject,
+    first_gaussian: u64,
+    num_gaussian: u64,
+}
+
+impl Renderer {
+    /// Limits the device passed to [`Renderer::new`] has to be created with, textures as large
+    /// as `adapter` supports for targets of several views side by side.
+    pub fn required_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+        let mut limit = wgpu::Limits::downlevel_defaults();
+        limit.max_texture_dimension_2d = adapter.limits().max_texture_dimension_2d;
+        limit.max_buffer_size = 2147483640;
+        limit.max_storage_buffer_binding_size = 2147483640;
+        limit.max_compute_workgroup_storage_size = 32768;
+        limit.max_storage_buffers_per_shader_stage = 10;
+        limit.max_buffer_size = 2147483640;
+
+        limit
+    }
+
+    /// Uploads `num_gaussian` packed `Gaussian` structs of preprocess.wgsl as the first object
+    /// of the scene and builds the pipelines for a `width` x `height` target of `target_format`.
+    /// The first object is empty without Gaussians, e.g. for a scene streamed by a
+    /// [`crate::Streamer`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target_format: wgpu::TextureFormat,
+        gaussians: &[f32],
+        num_gaussian: u64,
+        width: u32,
+        height: u32,
+        settings: RenderSettings,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("default bind group"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 9,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 10,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: CHANNEL_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 11,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 12,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 13,
+                    visibility: ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 14,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 15,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // preprocess does not sort, its second group holds the objects, crop volumes, the
+        // state of the Gaussians and their LOD nodes instead
+        let object_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("object bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let sort_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("sorter bind group layout"),
+                entries: &(0..7)
+                    .map(|binding| BindGroupLayoutEntry {
+                        binding,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    })
+                    .collect::<Vec<_>>(),
+            });
+
+        // rasterize only reads the sorted values and ranges, leaving room for the stats
+        let tile_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("tile bind group layout"),
+                entries: &[true, true, false]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(binding, read_only)| BindGroupLayoutEntry {
+                        binding: binding as u32,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    })
+                    .collect::<Vec<_>>(),
+            });
+
+        let stats_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("stats bind group layout"),
+                entries: &[
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                    wgpu::BufferBindingType::Uniform,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                ]
+                .into_iter()
+                .enumerate()
+                .map(|(binding, ty)| BindGroupLayoutEntry {
+                    binding: binding as u32,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                })
+                .collect::<Vec<_>>(),
+            });
+
+        let depth_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("depth bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, &sort_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let preprocess_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("preprocess pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, &object_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let rasterize_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("rasterize pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &tile_bind_group_layout,
+                &depth_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/preprocess.wgsl"))),
+        });
+
+        let preprocess_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Preprocess pipeline"),
+            layout: Some(&preprocess_pipeline_layout),
+            module: &cs_module,
+            entry_point: "main",
+        });
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/rasterize.wgsl"))),
+        });
+
+        let rasterize_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("rasterize pipeline"),
+            layout: Some(&rasterize_pipeline_layout),
+            module: &cs_module,
+            entry_point: "main",
+        });
+
+        let debug_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("debug rasterize pipeline"),
+            layout: Some(&rasterize_pipeline_layout),
+            module: &cs_module,
+            entry_point: "debug",
+        });
+
+        let overlay = Overlay::new(device, target_format);
+        let picker = Picker::new(
+            device,
+            &cs_module,
+            &[
+                &bind_group_layout,
+                &tile_bind_group_layout,
+                &depth_bind_group_layout,
+            ],
+        );
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("util copute shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/util.wgsl"))),
+        });
+
+        let prefix_sum_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("prefix sum pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &cs_module,
+            entry_point: "compute_prefix_sum",
+        });
+
+        let finish_prefix_sum_pipeline =
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("finish prefix sum pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &cs_module,
+                entry_point: "finish_prefix_sum",
+            });
+
+        let copy_pair_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("copy_pair pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &cs_module,
+            entry_point: "copy_key_value",
+        });
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("range compute shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/range.wgsl"))),
+        });
+        let range_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("range pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &cs_module,
+            entry_point: "compute_range",
+        });
+
+        let stats_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("stats pipeline layout"),
+            bind_group_layouts: &[&stats_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("stats compute shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/stats.wgsl"))),
+        });
+        let count_visible_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("count visible pipeline"),
+            layout: Some(&stats_pipeline_layout),
+            module: &cs_module,
+            entry_point: "count_visible",
+        });
+        let tile_stats_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("tile stats pipeline"),
+            layout: Some(&stats_pipeline_layout),
+            module: &cs_module,
+            entry_point: "tile_stats",
+        });
+
+        // render pipeline
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/render.wgsl"))),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &cs_module,
+                entry_point: "vert_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &cs_module,
+                entry_point: "frag_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        // create buffer
+        let gaussian_buffer = create_gaussian_buffer(device, num_gaussian);
+        queue.write_buffer(&gaussian_buffer, 0, bytemuck::cast_slice(gaussians));
+
+        let num_splat = num_gaussian;
+        let state_buffer = create_state_buffer(device, num_gaussian);
+        let lod_buffer = create_lod_buffer(device, num_gaussian);
+        queue.write_buffer(
+            &lod_buffer,
+            0,
+            bytemuck::cast_slice(&vec![LodUniform::NONE; num_gaussian as usize]),
+        );
+        let editor = Editor::new(device);
+        let splat_buffer = create_splat_buffer(device, num_splat);
+
+        let views: Vec<ViewBuffers> = (0..MAX_VIEWS).map(|_| ViewBuffers::new(device)).collect();
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("settings"),
+            size: std::mem::size_of::<SettingsUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let transform = Transform::default();
+        let objects = vec![ObjectSlot {
+            object: SceneObject::default(),
+            first_gaussian: 0,
+            num_gaussian,
+        }];
+        let object_buffer = create_object_buffer(device, &object_uniforms(&objects, &transform));
+        let crops = Vec::new();
+        let crop_buffer = create_crop_buffer(device, &crops);
+
+        // sorting
+        let subgroup_size = guess_workgroup_size(device, queue).await.unwrap();
+        let sorter = GPUSorter::new(device, subgroup_size);
+
+        let sort_buffers = sorter.create_sort_buffers(device, NonZeroU32::new(NUM_SLPAT).unwrap());
+        let sort_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sort size"),
+            contents: bytemuck::cast_slice(0_u32.to_ne_bytes().as_slice()),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+        let prefix_sum_buffer = create_prefix_sum_buffer(device, num_splat);
+
+        let sort_dispatch_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sort dispatch buffer"),
+            size: 4 * 4,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::INDIRECT,
+            mapped_at_creation: false,
+        });
+
+        let range_dispatch_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("range dispatch buffer"),
+            size: 4 * 4,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::INDIRECT,
+            mapped_at_creation: false,
+        });
+
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("environment sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let no_depth_test_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("no depth test"),
+            contents: bytemuck::bytes_of::<DepthTestUniform>(&bytemuck::Zeroable::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let no_depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("no depth"),
+            size: wgpu::Extent3d::default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let no_depth_bind_group = create_bind_group(
+            device,
+            &depth_bind_group_layout,
+            &[
+                wgpu::BindingResource::TextureView(
+                    &no_depth_texture.create_view(&Default::default()),
+                ),
+                no_depth_test_buffer.as_entire_binding(),
+            ],
+        );
+
+        let output_buffer = create_output_buffer(device, width, height);
+        let range_buffer = create_range_buffer(device, width, height);
+        let stats_buffer = create_stats_buffer(device, width, height, &settings);
+        let channel_texture = create_channel_texture(device, width, height, &settings);
+        let environment_texture = create_environment_texture(device, queue, &settings);
+
+        let channel_view = channel_texture.create_view(&Default::default());
+        let environment_view = environment_texture.create_view(&Default::default());
+        let bind_groups = views
+            .iter()
+            .map(|view| {
+                create_bind_group(
+                    device,
+                    &bind_group_layout,
+                    &[
+                        gaussian_buffer.as_entire_binding(),
+                        splat_buffer.as_entire_binding(),
+                        view.view_matrix.as_entire_binding(),
+                        view.proj_matrix.as_entire_binding(),
+                        view.focal.as_entire_binding(),
+                        view.tan_fov.as_entire_binding(),
+                        view.screen.as_entire_binding(),
+                        view.camera.as_entire_binding(),
+                        output_buffer.as_entire_binding(),
+                        settings_buffer.as_entire_binding(),
+                        wgpu::BindingResource::TextureView(&channel_view),
+                        wgpu::BindingResource::TextureView(&environment_view),
+                        wgpu::BindingResource::Sampler(&environment_sampler),
+                        view.origin.as_entire_binding(),
+                        view.principal.as_entire_binding(),
+                        view.projection.as_entire_binding(),
+                    ],
+                )
+            })
+            .collect();
+        let sort_bind_group = create_bind_group(
+            device,
+            &sort_bind_group_layout,
+            &[
+                sort_buffers.keys().as_entire_binding(),
+                sort_buffers.values().as_entire_binding(),
+                sort_size_buffer.as_entire_binding(),
+                prefix_sum_buffer.as_entire_binding(),
+                range_buffer.as_entire_binding(),
+                sort_dispatch_buffer.as_entire_binding(),
+                range_dispatch_buffer.as_entire_binding(),
+            ],
+        );
+        let tile_bind_group = create_bind_group(
+            device,
+            &tile_bind_group_layout,
+            &[
+                sort_buffers.values().as_entire_binding(),
+                range_buffer.as_entire_binding(),
+                stats_buffer.as_entire_binding(),
+            ],
+        );
+        let stats_bind_group = create_bind_group(
+            device,
+            &stats_bind_group_layout,
+            &[
+                splat_buffer.as_entire_binding(),
+                range_buffer.as_entire_binding(),
+                views[0].screen.as_entire_binding(),
+                stats_buffer.as_entire_binding(),
+            ],
+        );
+
+        queue.write_buffer(
+            &settings_buffer,
+            0,
+            bytemuck::bytes_of(&SettingsUniform::from(&settings)),
+        );
+
+        let profiler = settings
+            .profile
+            .then(|| Profiler::new(device, queue))
+            .flatten();
+        let stats_readback = settings
+            .stats
+            .then(|| Readback::new(device, "stats staging buffer", STATS_HEADER_SIZE));
+
+        let object_bind_group = create_bind_group(
+            device,
+            &object_bind_group_layout,
+            &[
+                object_buffer.as_entire_binding(),
+                crop_buffer.as_entire_binding(),
+                state_buffer.as_entire_binding(),
+                lod_buffer.as_entire_binding(),
+            ],
+        );
+
+        Self {
+            num_gaussian,
+            state_buffer,
+            lod_buffer,
+            editor,
+            picker,
+            overlay,
+            num_splat,
+            width,
+            height,
+            settings,
+            bind_group_layout,
+            sort_bind_group_layout,
+            tile_bind_group_layout,
+            depth_bind_group_layout,
+            stats_bind_group_layout,
+            object_bind_group_layout,
+            preprocess_pipeline,
+            prefix_sum_pipeline,
+            finish_prefix_sum_pipeline,
+            copy_pair_pipeline,
+            range_pipeline,
+            rasterize_pipeline,
+            debug_pipeline,
+            render_pipeline,
+            count_visible_pipeline,
+            tile_stats_pipeline,
+            sorter,
+            sort_buffers,
+            gaussian_buffer,
+            splat_buffer,
+            views,
+            output_buffer,
+            settings_buffer,
+            transform,
+            objects,
+            object_buffer,
+            crops,
+            crop_buffer,
+            channel_texture,
+            environment_texture,
+            environment_sampler,
+            sort_size_buffer,
+            prefix_sum_buffer,
+            range_buffer,
+            sort_dispatch_buffer,
+            range_dispatch_buffer,
+            profiler,
+            stats_buffer,
+            stats_readback,
+            no_depth_bind_group,
+            bind_groups,
+            sort_bind_group,
+            tile_bind_group,
+            stats_bind_group,
+            object_bind_group,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Number of duplicated tile keys of the last frame, in a single u32.
+    pub fn sort_size_buffer(&self) -> &wgpu::Buffer {
+        &self.sort_size_buffer
+    }
+
+    /// Reallocates the per pixel and per tile buffers for a new target size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        self.output_buffer = create_output_buffer(device, width, height);
+        self.range_buffer = create_range_buffer(device, width, height);
+        self.stats_buffer = create_stats_buffer(device, width, height, &self.settings);
+        self.channel_texture = create_channel_texture(device, width, height, &self.settings);
+        self.create_bind_groups(device);
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Places the scene in world space, takes effect with the next frame.
+    pub fn set_transform(&mut self, queue: &wgpu::Queue, transform: Transform) {
+        self.transform = transform;
+        self.write_objects(queue);
+    }
+
+    /// GPU memory of the Gaussians and splats of the scene, the part that grows with the
+    /// objects.
+    pub(crate) fn scene_size(&self) -> u64 {
+        self.num_gaussian * (GAUSSIAN_SIZE + STATE_SIZE + LOD_SIZE)
+            + self.num_splat * (SPLAT_SIZE + 4)
+    }
+
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn object(&self, index: usize) -> Option<&SceneObject> {
+        self.objects.get(index).map(|slot| &slot.object)
+    }
+
+    /// Moves, hides or fades the object `index`, takes effect with the next frame.
+    pub fn set_object(
+        &mut self,
+        queue: &wgpu::Queue,
+        index: usize,
+        object: SceneObject,
+    ) -> Result<(), String> {
+        let slot = self
+            .objects
+            .get_mut(index)
+            .ok_or_else(|| format!("no object {index}"))?;
+        slot.object = object;
+        self.write_objects(queue);
+        Ok(())
+    }
+
+    /// Uploads `num_gaussian` more packed `Gaussian` structs as a new object, sorted and
+    /// composited together with the others. Returns its index. Fails and adds nothing when
+    /// `gaussians` holds another number of them.
+    pub fn add_object(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gaussians: &[f32],
+        num_gaussian: u64,
+    ) -> Result<usize, String> {
+        if gaussians.len() as u64 != num_gaussian * GAUSSIAN_FLOATS as u64 {
+            return Err(format!(
+                "{} floats are not {num_gaussian} Gaussians",
+                gaussians.len()
+            ));
+        }
+
+        let first_gaussian = self.grow(device, queue, num_gaussian);
+        queue.write_buffer(
+            &self.gaussian_buffer,
+            first_gaussian * GAUSSIAN_SIZE,
+            bytemuck::cast_slice(gaussians),
+        );
+
+        Ok(self
+            .push_objects(
+                device,
+                [ObjectSlot {
+                    object: SceneObject::default(),
+                    first_gaussian,
+                    num_gaussian,
+                }],
+            )
+            .start)
+    }
+
+    /// Adds `count` objects with room for `num_gaussian` Gaussians each, drawing nothing until
+    /// filled with [`Renderer::write_object`]. Returns their indices.
+    ///
+    /// Like every added object, this reallocates the buffers of the scene and copies the
+    /// Gaussians already there, which take twice their memory until the copy is done.
+    pub fn reserve_objects(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        count: usize,
+        num_gaussian: u64,
+    ) -> Range<usize> {
+        let first_gaussian = self.grow(device, queue, count as u64 * num_gaussian);
+        queue.write_buffer(
+            &self.state_buffer,
+            first_gaussian * STATE_SIZE,
+            bytemuck::cast_slice(&vec![STATE_DELETED; count * num_gaussian as usize]),
+        );
+
+        self.push_objects(
+            device,
+            (0..count as u64).map(|i| ObjectSlot {
+                object: SceneObject::default(),
+                first_gaussian: first_gaussian + i * num_gaussian,
+                num_gaussian,
+            }),
+        )
+    }
+
+    /// Replaces the Gaussians of object `index`, and of its instances, with `num_gaussian`
+    /// packed `Gaussian` structs, unselected and visible. The rest of its room stays empty.
+    /// Takes effect with the next frame. Fails and writes nothing when the object has no room
+    /// for them or `gaussians` holds fewer.
+    pub fn write_object(
+        &self,
+        queue: &wgpu::Queue,
+        index: usize,
+        gaussians: &[f32],
+        num_gaussian: u64,
+    ) -> Result<(), String> {
+        let slot = self
+            .objects
+            .get(index)
+            .ok_or_else(|| format!("no object {index}"))?;
+        if num_gaussian > slot.num_gaussian {
+            return Err(format!(
+                "object {index} has room for {} Gaussians, not {num_gaussian}",
+                slot.num_gaussian
+            ));
+        }
+        let gaussians = gaussians
+            .get(..num_gaussian as usize * GAUSSIAN_FLOATS)
+            .ok_or_else(|| {
+                format!(
+                    "{} floats hold fewer than {num_gaussian} Gaussians",
+                    gaussians.len()
+                )
+            })?;
+
+        let mut states = vec![0; slot.num_gaussian as usize];
+        states[num_gaussian as usize..].fill(STATE_DELETED);
+
+        queue.write_buffer(
+            &self.gaussian_buffer,
+            slot.first_gaussian * GAUSSIAN_SIZE,
+            bytemuck::cast_slice(gaussians),
+        );
+        queue.write_buffer(
+            &self.state_buffer,
+            slot.first_gaussian * STATE_SIZE,
+            bytemuck::cast_slice(&states),
+        );
+        queue.write_buffer(
+            &self.lod_buffer,
+            slot.first_gaussian * LOD_SIZE,
+            bytemuck::cast_slice(&vec![LodUniform::NONE; slot.num_gaussian as usize]),
+        );
+
+        Ok(())
+    }
+
+    /// Writes packed `Gaussian` structs to the room of object `index` from `first` on,
+    /// unselected and visible. Takes effect with the next frame.
+    pub(crate) fn write_gaussians(
+        &self,
+        queue: &wgpu::Queue,
+        index: usize,
+        first: u64,
+        gaussians: &[f32],
+    ) {
+        let first_gaussian = self.objects[index].first_gaussian + first;
+        let num_gaussian = gaussians.len() / GAUSSIAN_FLOATS;
+        queue.write_buffer(
+            &self.gaussian_buffer,
+            first_gaussian * GAUSSIAN_SIZE,
+            bytemuck::cast_slice(gaussians),
+        );
+        queue.write_buffer(
+            &self.state_buffer,
+            first_gaussian * STATE_SIZE,
+            bytemuck::cast_slice(&vec![0_u32; num_gaussian]),
+        );
+    }
+
+    /// Empties the room `slots` of object `index`. Takes effect with the next frame.
+    pub(crate) fn clear_gaussians(&self, queue: &wgpu::Queue, index: usize, slots: Range<u64>) {
+        queue.write_buffer(
+            &self.state_buffer,
+            (self.objects[index].first_gaussian + slots.start) * STATE_SIZE,
+            bytemuck::cast_slice(&vec![STATE_DELETED; (slots.end - slots.start) as usize]),
+        );
+    }
+
+    /// Uploads the Gaussians of `tree` as a new object drawn as the cut of the hierarchy at
+    /// [`RenderSettings::lod_threshold`]. Returns its index.
+    ///
+    /// The whole hierarchy is resident and preprocessed every frame, a [`crate::LodCut`]
+    /// bounds both for trees too large for that.
+    pub fn add_lod_object(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tree: &LodTree,
+    ) -> usize {
+        let index = self
+            .add_object(device, queue, &tree.gaussians, tree.num_gaussian)
+            .expect("a LOD tree holds its Gaussians");
+        self.write_lod(queue, index, tree);
+        index
+    }
+
+    /// Draws object `index`, whose Gaussians were uploaded from `tree`, as the cut of the
+    /// hierarchy, e.g. the first object of a renderer created with `tree.gaussians`. Its
+    /// instances share the hierarchy. Fails when the object has another number of Gaussians.
+    pub fn set_lod(&self, queue: &wgpu::Queue, index: usize, tree: &LodTree) -> Result<(), String> {
+        match self.objects.get(index) {
+            Some(slot) if slot.num_gaussian == tree.num_gaussian => {
+                self.write_lod(queue, index, tree);
+                Ok(())
+            }
+            Some(slot) => Err(format!(
+                "object {index} of {} Gaussians was not uploaded from a LOD tree of {}",
+                slot.num_gaussian, tree.num_gaussian
+            )),
+            None => Err(format!("no object {index}")),
+        }
+    }
+
+    fn write_lod(&self, queue: &wgpu::Queue, index: usize, tree: &LodTree) {
+        queue.write_buffer(
+            &self.lod_buffer,
+            self.objects[index].first_gaussian * LOD_SIZE,
+            bytemuck::cast_slice(tree.nodes()),
+        );
+    }
+
+    /// Places the Gaussians of object `index` in the scene once more, without uploading them
+    /// again. The new object starts where `index` is and can be moved with
+    /// [`Renderer::set_object`]. Returns its index, fails for an unknown object.
+    pub fn add_instance(&mut self, device: &wgpu::Device, index: usize) -> Result<usize, String> {
+        let slot = self
+            .objects
+            .get(index)
+            .ok_or_else(|| format!("no object {index}"))?;
+
+        let slot = ObjectSlot {
+            object: slot.object.clone(),
+            first_gaussian: slot.first_gaussian,
+            num_gaussian: slot.num_gaussian,
+        };
+
+        Ok(self.push_objects(device, [slot]).start)
+    }
+
+    /// Room for `num_gaussian` more Gaussians after the others, unselected, visible and
+    /// outside of any LOD hierarchy. Returns the first one.
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, num_gaussian: u64) -> u64 {
+        let first_gaussian = self.num_gaussian;
+        let total = first_gaussian + num_gaussian;
+        let gaussian_buffer = create_gaussian_buffer(device, total);
+        let state_buffer = create_state_buffer(device, total);
+        let lod_buffer = create_lod_buffer(device, total);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("add object encoder"),
+        });
+        for (old, new, size) in [
+            (&self.gaussian_buffer, &gaussian_buffer, GAUSSIAN_SIZE),
+            (&self.state_buffer, &state_buffer, STATE_SIZE),
+            (&self.lod_buffer, &lod_buffer, LOD_SIZE),
+        ] {
+            encoder.copy_buffer_to_buffer(old, 0, new, 0, first_gaussian * size);
+        }
+        queue.write_buffer(
+            &lod_buffer,
+            first_gaussian * LOD_SIZE,
+            bytemuck::cast_slice(&vec![LodUniform::NONE; num_gaussian as usize]),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.gaussian_buffer = gaussian_buffer;
+        self.state_buffer = state_buffer;
+        self.lod_buffer = lod_buffer;
+        self.num_gaussian = total;
+
+        first_gaussian
+    }
+
+    /// Gives `slots` splats of their own and rebuilds what depends on their number.
+    fn push_objects(
+        &mut self,
+        device: &wgpu::Device,
+        slots: impl IntoIterator<Item = ObjectSlot>,
+    ) -> Range<usize> {
+        let first = self.objects.len();
+        for slot in slots {
+            self.num_splat += slot.num_gaussian;
+            self.objects.push(slot);
+        }
+
+        self.splat_buffer = create_splat_buffer(device, self.num_splat);
+        self.prefix_sum_buffer = create_prefix_sum_buffer(device, self.num_splat);
+        self.object_buffer =
+            create_object_buffer(device, &object_uniforms(&self.objects, &self.transform));
+        self.create_bind_groups(device);
+
+        first..self.objects.len()
+    }
+
+    pub fn crops(&self) -> &[Crop] {
+        &self.crops
+    }
+
+    /// Discards the Gaussians outside of `crops` from the next frame on, nothing is cropped
+    /// without any.
+    pub fn set_crops(&mut self, device: &wgpu::Device, crops: Vec<Crop>) {
+        self.crop_buffer = create_crop_buffer(device, &crops);
+        self.crops = crops;
+        self.create_bind_groups(device);
+    }
+
+    /// Selects the Gaussians of `selector`, combined with the current selection by `mode`.
+    pub fn select(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        selector: &Selector,
+        mode: SelectMode,
+    ) {
+        self.editor
+            .select(device, queue, &self.edit_buffers(), selector, mode);
+    }
+
+    pub fn clear_selection(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.edit(device, queue, Op::Clear, Vector3::zeros());
+    }
+
+    /// Hides the selected Gaussians until [`Renderer::show_hidden`], they are unselected.
+    pub fn hide_selection(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.edit(device, queue, Op::Hide, Vector3::zeros());
+    }
+
+    pub fn show_hidden(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.edit(device, queue, Op::Show, Vector3::zeros());
+    }
+
+    /// Removes the selected Gaussians for good, they are left out of
+    /// [`Renderer::read_gaussians`].
+    pub fn delete_selection(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.edit(device, queue, Op::Delete, Vector3::zeros());
+    }
+
+    /// Paints the selected Gaussians in the flat `color`, rgb in [0, 1].
+    pub fn recolor_selection(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color: Vector3<f32>,
+    ) {
+        self.edit(device, queue, Op::Recolor, color);
+    }
+
+    /// Packed `Gaussian` structs of the object `index` with the edits applied, deleted
+    /// Gaussians left out. Instances read the Gaussians of the object they were made from.
+    /// Fails for an unknown object.
+    pub async fn read_gaussians(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+    ) -> Result<Vec<f32>, String> {
+        let slot = self
+            .objects
+            .get(index)
+            .ok_or_else(|| format!("no object {index}"))?;
+        if slot.num_gaussian == 0 {
+            return Ok(Vec::new());
+        }
+
+        let staging_buffer = |size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("edit staging buffer"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let gaussian_staging = staging_buffer(slot.num_gaussian * GAUSSIAN_SIZE);
+        let state_staging = staging_buffer(slot.num_gaussian * STATE_SIZE);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("gaussian readback encoder"),
+        });
+        encoder.copy_buffer_to_buffer(
+            &self.gaussian_buffer,
+            slot.first_gaussian * GAUSSIAN_SIZE,
+            &gaussian_staging,
+            0,
+            gaussian_staging.size(),
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.state_buffer,
+            slot.first_gaussian * STATE_SIZE,
+            &state_staging,
+            0,
+            state_staging.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let gaussians = read_buffer(device, &gaussian_staging).await;
+        let states = read_buffer(device, &state_staging).await;
+        let states: &[u32] = bytemuck::cast_slice(&states);
+
+        Ok(bytemuck::cast_slice::<u8, f32>(&gaussians)
+            .chunks_exact(GAUSSIAN_FLOATS)
+            .zip(states)
+            .filter(|(_, state)| *state & STATE_DELETED == 0)
+            .flat_map(|(gaussian, _)| gaussian.iter().copied())
+            .collect())
+    }
+
+    /// Dominant Gaussian and expected surface under `pixel` of a view through `camera`,
+    /// `None` when nothing is seen there. Opaque geometry is ignored.
+    ///
+    /// Sorts the splats for `camera` again in the buffers of the first view, overwriting the
+    /// sort of the last frame there. A pick between stereo frames, or with another camera,
+    /// leaves the first view sorted for `camera` until the next frame sorts it again.
+    pub async fn pick(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        pixel: Vector2<u32>,
+    ) -> Option<Pick> {
+        let bind_group = &self.bind_groups[0];
+        self.update_view(queue, &self.views[0], camera, [0, 0]);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("pick encoder"),
+        });
+        self.sort_splats(&mut encoder, bind_group, None);
+        self.picker.record(
+            queue,
+            &mut encoder,
+            &[bind_group, &self.tile_bind_group, &self.no_depth_bind_group],
+            pixel,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.picker.read(device, |splat| self.locate(splat)).await
+    }
+
+    /// Draws `measurements` as lines over `view`, which was rendered before in `encoder`.
+    /// `color` is not premultiplied.
+    pub fn render_measurements(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &View,
+        measurements: &[Measurement],
+        color: [f32; 4],
+    ) {
+        self.overlay.record(
+            device,
+            encoder,
+            view,
+            self.width,
+            self.height,
+            measurements,
+            color,
+        );
+    }
+
+    /// Object of `splat` and its Gaussian counted from the first of the object.
+    fn locate(&self, splat: u64) -> (usize, u64) {
+        let mut first_splat = 0;
+        for (index, slot) in self.objects.iter().enumerate() {
+            if splat < first_splat + slot.num_gaussian {
+                return (index, splat - first_splat);
+            }
+            first_splat += slot.num_gaussian;
+        }
+
+        unreachable!("splat {splat} is past the last object")
+    }
+
+    fn edit(&self, device: &wgpu::Device, queue: &wgpu::Queue, op: Op, color: Vector3<f32>) {
+        self.editor
+            .apply(device, queue, &self.edit_buffers(), op, color);
+    }
+
+    fn edit_buffers(&self) -> EditBuffers<'_> {
+        EditBuffers {
+            gaussians: &self.gaussian_buffer,
+            state: &self.state_buffer,
+            objects: &self.object_buffer,
+            num_gaussian: self.num_gaussian,
+            num_splat: self.num_splat,
+            screen: Vector2::new(self.width, self.height),
+        }
+    }
+
+    fn write_objects(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.object_buffer,
+            0,
+            bytemuck::cast_slice(&object_uniforms(&self.objects, &self.transform)),
+        );
+    }
+
+    pub fn set_settings(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        settings: RenderSettings,
+    ) {
+        self.settings = settings;
+        self.profiler = self
+            .settings
+            .profile
+            .then(|| Profiler::new(device, queue))
+            .flatten();
+        self.stats_readback = self
+            .settings
+            .stats
+            .then(|| Readback::new(device, "stats staging buffer", STATS_HEADER_SIZE));
+        self.stats_buffer = create_stats_buffer(device, self.width, self.height, &self.settings);
+        self.channel_texture =
+            create_channel_texture(device, self.width, self.height, &self.settings);
+        self.environment_texture = create_environment_texture(device, queue, &self.settings);
+        self.create_bind_groups(device);
+
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&SettingsUniform::from(&self.settings)),
+        );
+    }
+
+    fn create_bind_groups(&mut self, device: &wgpu::Device) {
+        let channel_view = self.channel_texture.create_view(&Default::default());
+        let environment_view = self.environment_texture.create_view(&Default::default());
+        self.bind_groups = self
+            .views
+            .iter()
+            .map(|view| {
+                create_bind_group(
+                    device,
+                    &self.bind_group_layout,
+                    &[
+                        self.gaussian_buffer.as_entire_binding(),
+                        self.splat_buffer.as_entire_binding(),
+                        view.view_matrix.as_entire_binding(),
+                        view.proj_matrix.as_entire_binding(),
+                        view.focal.as_entire_binding(),
+                        view.tan_fov.as_entire_binding(),
+                        view.screen.as_entire_binding(),
+                        view.camera.as_entire_binding(),
+                        self.output_buffer.as_entire_binding(),
+                        self.settings_buffer.as_entire_binding(),
+                        wgpu::BindingResource::TextureView(&channel_view),
+                        wgpu::BindingResource::TextureView(&environment_view),
+                        wgpu::BindingResource::Sampler(&self.environment_sampler),
+                        view.origin.as_entire_binding(),
+                        view.principal.as_entire_binding(),
+                        view.projection.as_entire_binding(),
+                    ],
+                )
+            })
+            .collect();
+        self.sort_bind_group = create_bind_group(
+            device,
+            &self.sort_bind_group_layout,
+            &[
+                self.sort_buffers.keys().as_entire_binding(),
+                self.sort_buffers.values().as_entire_binding(),
+                self.sort_size_buffer.as_entire_binding(),
+                self.prefix_sum_buffer.as_entire_binding(),
+                self.range_buffer.as_entire_binding(),
+                self.sort_dispatch_buffer.as_entire_binding(),
+                self.range_dispatch_buffer.as_entire_binding(),
+            ],
+        );
+        self.tile_bind_group = create_bind_group(
+            device,
+            &self.tile_bind_group_layout,
+            &[
+                self.sort_buffers.values().as_entire_binding(),
+                self.range_buffer.as_entire_binding(),
+                self.stats_buffer.as_entire_binding(),
+            ],
+        );
+        self.stats_bind_group = create_bind_group(
+            device,
+            &self.stats_bind_group_layout,
+            &[
+                self.splat_buffer.as_entire_binding(),
+                self.range_buffer.as_entire_binding(),
+                self.views[0].screen.as_entire_binding(),
+                self.stats_buffer.as_entire_binding(),
+            ],
+        );
+
+        self.object_bind_group = create_bind_group(
+            device,
+            &self.object_bind_group_layout,
+            &[
+                self.object_buffer.as_entire_binding(),
+                self.crop_buffer.as_entire_binding(),
+                self.state_buffer.as_entire_binding(),
+                self.lod_buffer.as_entire_binding(),
+            ],
+        );
+    }
+
+    fn update_view(
+        &self,
+        queue: &wgpu::Queue,
+        buffers: &ViewBuffers,
+        camera: &Camera,
+        origin: [u32; 2],
+    ) {
+        let screen = nalgebra::Vector2::<u32>::new(self.width, self.height);
+        // the frustum is off center with the principal point, clamp to its wider side
+        let extent = camera.principal.zip_map(&screen, |principal, size| {
+            principal.max(size as f32 - principal)
+        });
+        let tan_fov = extent.component_div(&camera.focal);
+
+        let proj_matrix = camera.projection(self.width, self.height, NEAR, FAR);
+        let vp_matrix = proj_matrix * camera.view;
+
+        queue.write_buffer(
+            &buffers.camera,
+            0,
+            bytemuck::cast_slice(camera.position().coords.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.view_matrix,
+            0,
+            bytemuck::cast_slice(camera.view.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.proj_matrix,
+            0,
+            bytemuck::cast_slice(vp_matrix.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.focal,
+            0,
+            bytemuck::cast_slice(camera.focal.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.tan_fov,
+            0,
+            bytemuck::cast_slice(tan_fov.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.principal,
+            0,
+            bytemuck::cast_slice(camera.principal.as_slice()),
+        );
+        queue.write_buffer(
+            &buffers.projection,
+            0,
+            bytemuck::bytes_of(&ProjectionUniform {
+                model: camera.model.id(),
+                _pad: [0; 3],
+                distortion: camera.model.distortion(),
+            }),
+        );
+        queue.write_buffer(&buffers.screen, 0, bytemuck::cast_slice(screen.as_slice()));
+        queue.write_buffer(&buffers.origin, 0, bytemuck::cast_slice(&origin));
+    }
+
+    /// Records all passes for `camera` into `encoder` and composites the result over `target`,
+    /// which must have the size and format the renderer was created with.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        camera: &Camera,
+        target: &RenderTarget,
+    ) {
+        self.record_views(
+            device,
+            queue,
+            encoder,
+            &[View {
+                camera,
+                target: *target,
+                origin: [0, 0],
+            }],
+        );
+    }
+
+    /// Renders the two eyes side by side into a `target` twice the renderer's width, the
+    /// "stereo-left-right" layout of a WebXR projection layer.
+    pub fn render_stereo(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        left: &Camera,
+        right: &Camera,
+        target: &RenderTarget,
+    ) {
+        self.record_views(
+            device,
+            queue,
+            encoder,
+            &[
+                View {
+                    camera: left,
+                    target: *target,
+                    origin: [0, 0],
+                },
+                View {
+                    camera: right,
+                    target: *target,
+                    origin: [self.width, 0],
+                },
+            ],
+        );
+    }
+
+    /// Records up to [`MAX_VIEWS`] views into `encoder`, each view the renderer's size.
+    ///
+    /// The scene, pipelines and intermediate buffers are shared, every view is preprocessed
+    /// and sorted on its own since the splats depend on its camera. Timings, stats and
+    /// channels are those of the first view. Fails and records nothing for more views.
+    pub fn render_views(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        views: &[View],
+    ) -> Result<(), String> {
+        if views.len() > MAX_VIEWS {
+            return Err(format!(
+                "{} views, at most {MAX_VIEWS} per frame",
+                views.len()
+            ));
+        }
+
+        self.record_views(device, queue, encoder, views);
+        Ok(())
+    }
+
+    fn record_views(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        views: &[View],
+    ) {
+        for (index, view) in views.iter().enumerate() {
+            self.render_view(device, queue, encoder, index, view);
+        }
+
+        if let Some(profiler) = &self.profiler {
+            profiler.resolve(encoder);
+        }
+    }
+
+    fn render_view(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        index: usize,
+        view: &View,
+    ) {
+        let buffers = &self.views[index];
+        let bind_group = &self.bind_groups[index];
+        self.update_view(queue, buffers, view.camera, view.origin);
+
+        let depth_bind_group = view.target.depth.as_ref().map(|depth| {
+            let p = depth.projection;
+            queue.write_buffer(
+                &buffers.depth_test,
+                0,
+                bytemuck::bytes_of(&DepthTestUniform {
+                    projection: [p[(2, 2)], p[(2, 3)], p[(3, 2)], p[(3, 3)]],
+                    enabled: 1,
+                    _pad: [0; 3],
+                }),
+            );
+
+            create_bind_group(
+                device,
+                &self.depth_bind_group_layout,
+                &[
+                    wgpu::BindingResource::TextureView(depth.view),
+                    buffers.depth_test.as_entire_binding(),
+                ],
+            )
+        });
+
+        let profiler = self.profiler.as_ref().filter(|_| index == 0);
+
+        if self.settings.stats {
+            encoder.clear_buffer(&self.stats_buffer, 0, None);
+        }
+
+        self.sort_splats(encoder, bind_group, profiler);
+
+        // rasterize
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(5)),
+            });
+
+            pass.set_pipeline(match self.settings.mode {
+                RenderMode::Color | RenderMode::ShBand => &self.rasterize_pipeline,
+                _ => &self.debug_pipeline,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.tile_bind_group, &[]);
+            pass.set_bind_group(
+                2,
+                depth_bind_group
+                    .as_ref()
+                    .unwrap_or(&self.no_depth_bind_group),
+                &[],
+            );
+            pass.dispatch_workgroups(
+                self.width.div_ceil(TILE_SZ),
+                self.height.div_ceil(TILE_SZ),
+                1,
+            );
+        }
+
+        if index == 0 {
+            if let Some(stats_readback) = &self.stats_readback {
+                self.record_stats(encoder, stats_readback);
+            }
+        }
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: view.target.color,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                timestamp_writes: profiler.map(|p| p.render_pass(6)),
+                ..Default::default()
+            });
+
+            let [x, y] = view.origin;
+            pass.set_viewport(
+                x as f32,
+                y as f32,
+                self.width as f32,
+                self.height as f32,
+                0.0,
+                1.0,
+            );
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.sort_bind_group, &[]);
+            pass.set_pipeline(&self.render_pipeline);
+            pass.draw(0..6, 0..1);
+        }
+    }
+
+    /// Projects the splats for the view of `bind_group` and sorts them into the tiles.
+    fn sort_splats(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: &wgpu::BindGroup,
+        profiler: Option<&Profiler>,
+    ) {
+        // tiles without splats keep the range of the previous frame otherwise
+        encoder.clear_buffer(&self.range_buffer, 0, None);
+
+        let num_splat = self.num_splat;
+
+        // preprocess
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(0)),
+            });
+            pass.set_pipeline(&self.preprocess_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.object_bind_group, &[]);
+            let (x, y) = dispatch_size(num_splat, WG_SIZE);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+
+        // prefix sum
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(1)),
+            });
+            pass.set_pipeline(&self.prefix_sum_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.sort_bind_group, &[]);
+            let (x, y) = dispatch_size(num_splat, WG_SIZE * 2);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+
+        // finish prefix sum
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(2)),
+            });
+            pass.set_pipeline(&self.finish_prefix_sum_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.sort_bind_group, &[]);
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        // copy key-value pair
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(3)),
+            });
+            pass.set_pipeline(&self.copy_pair_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.sort_bind_group, &[]);
+            let (x, y) = dispatch_size(num_splat, WG_SIZE);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+
+        // sort
+        encoder.copy_buffer_to_buffer(
+            &self.sort_size_buffer,
+            0,
+            self.sort_buffers.state_buffer(),
+            0,
+            4,
+        );
+        self.sorter
+            .sort_indirect(encoder, &self.sort_buffers, &self.sort_dispatch_buffer);
+
+        // compute range
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: profiler.map(|p| p.compute_pass(4)),
+            });
+            pass.set_pipeline(&self.range_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.sort_bind_group, &[]);
+
+            let size = (NUM_SLPAT as f64).sqrt().ceil() as u64;
+            let size = size.div_ceil(8) as u32;
+
+            // pass.dispatch_workgroups_indirect(&range_dispatch_buffer, 0);
+
+            pass.dispatch_workgroups(size, size, 1);
+        }
+    }
+
+    fn record_stats(&self, encoder: &mut wgpu::CommandEncoder, stats_readback: &Readback) {
+        encoder.copy_buffer_to_buffer(
+            &self.sort_size_buffer,
+            0,
+            &self.stats_buffer,
+            STATS_KEYS_OFFSET,
+            4,
+        );
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("stats pass"),
+                timestamp_writes: None,
+            });
+            pass.set_bind_group(0, &self.stats_bind_group, &[]);
+
+            pass.set_pipeline(&self.count_visible_pipeline);
+            let (x, y) = dispatch_size(self.num_splat, WG_SIZE);
+            pass.dispatch_workgroups(x, y, 1);
+
+            let num_tile = self.width.div_ceil(TILE_SZ) * self.height.div_ceil(TILE_SZ);
+            pass.set_pipeline(&self.tile_stats_pipeline);
+            pass.dispatch_workgroups(num_tile.div_ceil(WG_SIZE as u32), 1, 1);
+        }
+
+        stats_readback.record(encoder, &self.stats_buffer);
+    }
+
+    /// Per stage GPU time of the newest submitted frame whose timestamps are mapped, without
+    /// waiting for the GPU. `None` while none is mapped yet and unless
+    /// [`RenderSettings::profile`] is set and the device supports timestamp queries.
+    pub fn read_timings(&self, device: &wgpu::Device) -> Option<StageTimings> {
+        self.profiler.as_ref()?.read(device)
+    }
+
+    /// Waits for the per stage GPU time of the last submitted frame, for a headless caller
+    /// reading every frame. `None` if it was already read and when not profiling.
+    pub async fn wait_timings(&self, device: &wgpu::Device) -> Option<StageTimings> {
+        self.profiler.as_ref()?.wait(device).await
+    }
+
+    /// Statistics of the newest submitted frame whose copy is mapped, without waiting for the
+    /// GPU. `None` while none is mapped yet and unless [`RenderSettings::stats`] is set; call it
+    /// every frame.
+    ///
+    /// Frames can keep being rendered while a copy is pending, their stats are dropped until
+    /// a staging buffer is free again.
+    pub fn read_stats(&self, device: &wgpu::Device) -> Option<FrameStats> {
+        let bytes = self.stats_readback.as_ref()?.poll(device)?;
+        Some(FrameStats::from_header(&bytes, self.num_splat))
+    }
+
+    /// Waits for the statistics of the last submitted frame, for a headless caller reading
+    /// every frame. `None` if they were already read and unless [`RenderSettings::stats`] is set.
+    pub async fn wait_stats(&self, device: &wgpu::Device) -> Option<FrameStats> {
+        let bytes = self.stats_readback.as_ref()?.wait(device).await?;
+        Some(FrameStats::from_header(&bytes, self.num_splat))
+    }
+
+    /// Reads back the channel texture written by the last [`Renderer::render`], 4 floats per
+    /// pixel: accumulated opacity, expected depth, median depth and 0.
+    /// `None` unless [`RenderSettings::output_channels`] is set.
+    pub async fn read_channels(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Option<Vec<f32>> {
+        if !self.settings.output_channels {
+            return None;
+        }
+
+        let bytes = read_texture(device, queue, &self.channel_texture).await;
+
+        Some(bytemuck::cast_slice(&bytes).to_vec())
+    }
+}
+
+/// Copies a 2D texture out after all submitted work is done, rows tightly packed.
+pub async fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Vec<u8> {
+    let size = texture.size();
+    let texel_size = texture
+        .format()
+        .block_copy_size(None)
+        .expect("texture format can not be copied");
+    let row_size = size.width * texel_size;
+    let bytes_per_row = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture staging buffer"),
+        size: bytes_per_row as u64 * size.height as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("texture readback encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &staging_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            depth_or_array_layers: 1,
+            ..size
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    // drop the row padding
+    read_buffer(device, &staging_buffer)
+        .await
+        .chunks(bytes_per_row as usize)
+        .flat_map(|row| row[..row_size as usize].to_vec())
+        .collect()
+}
+
+/// Workgroups of `workgroup_size` threads for `num_thread` threads, in rows of at most
+/// [`MAX_WORKGROUPS`]. Shaders number them `workgroup_id.y * num_workgroups.x + workgroup_id.x`.
+pub(crate) fn dispatch_size(num_thread: u64, workgroup_size: u64) -> (u32, u32) {
+    let num_workgroup = num_thread.div_ceil(workgroup_size);
+    let x = num_workgroup.min(MAX_WORKGROUPS);
+    (x as u32, num_workgroup.div_ceil(x.max(1)) as u32)
+}
+
+/// Maps `buffer` after all submitted work is done and copies its content out.
+pub(crate) async fn read_buffer(device: &wgpu::Device, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let buffer_slice = buffer.slice(..);
+    let (sender, receiver) = futures_channel::oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .await
+        .expect("communicaiton failed")
+        .expect("buffer reading failed");
+
+    let bytes = buffer_slice.get_mapped_range().to_vec();
+    buffer.unmap();
+
+    bytes
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    resources: &[wgpu::BindingResource],
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.clone(),
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn object_uniforms(objects: &[ObjectSlot], scene: &Transform) -> Vec<ObjectUniform> {
+    objects
+        .iter()
+        .scan(0, |first_splat, slot| {
+            // every instance of a Gaussian has a splat
+            let uniform =
+                ObjectUniform::new(&slot.object, scene, slot.first_gaussian, *first_splat);
+            *first_splat += slot.num_gaussian;
+            Some(uniform)
+        })
+        .collect()
+}
+
+fn create_object_buffer(device: &wgpu::Device, objects: &[ObjectUniform]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("objects"),
+        contents: bytemuck::cast_slice(objects),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn create_crop_buffer(device: &wgpu::Device, crops: &[Crop]) -> wgpu::Buffer {
+    let mut uniforms: Vec<CropUniform> = crops.iter().map(CropUniform::from).collect();
+    // bindings can not be empty
+    if uniforms.is_empty() {
+        uniforms.push(CropUniform::NONE);
+    }
+
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("crops"),
+        contents: bytemuck::cast_slice(&uniforms),
+        usage: wgpu::BufferUsages::STORAGE,
+    })
+}
+
+// bindings can not be empty, a scene without Gaussians gets room for one
+fn create_gaussian_buffer(device: &wgpu::Device, num_gaussian: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Gaussian"),
+        size: num_gaussian.max(1) * GAUSSIAN_SIZE,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_lod_buffer(device: &wgpu::Device, num_gaussian: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("LOD"),
+        size: num_gaussian.max(1) * LOD_SIZE,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_state_buffer(device: &wgpu::Device, num_gaussian: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Gaussian state"),
+        size: num_gaussian.max(1) * STATE_SIZE,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_splat_buffer(device: &wgpu::Device, num_splat: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Splat"),
+        size: num_splat.max(1) * SPLAT_SIZE,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_prefix_sum_buffer(device: &wgpu::Device, num_splat: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("prefix sum buffer"),
+        size: num_splat.max(1) * 4,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_output_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("output"),
+        size: width as u64 * height as u64 * 16,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_range_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+    let num_tile = width.div_ceil(TILE_SZ) * height.div_ceil(TILE_SZ);
+
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("range buffer"),
+        size: num_tile as u64 * 8 * 4,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+/// stats header and a counter per tile, the header only when stats are off
+fn create_stats_buffer(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    settings: &RenderSettings,
+) -> wgpu::Buffer {
+    let num_tile = if settings.stats {
+        width.div_ceil(TILE_SZ) * height.div_ceil(TILE_SZ)
+    } else {
+        1
+    };
+
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("stats buffer"),
+        size: STATS_HEADER_SIZE + num_tile as u64 * 4,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+/// accumulated opacity, expected depth and median depth
+fn create_channel_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    settings: &RenderSettings,
+) -> wgpu::Texture {
+    let size = if settings.output_channels {
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        }
+    } else {
+        wgpu::Extent3d::default()
+    };
+
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("channels"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: CHANNEL_FORMAT,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// environment map, a single black texel when the background is a color or a malformed image,
+/// downsampled to the largest texture of the device
+fn create_environment_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    settings: &RenderSettings,
+) -> wgpu::Texture {
+    let (width, height, pixels) = match &settings.background {
+        Background::Environment {
+            width,
+            height,
+            pixels,
+        } if *width > 0
+            && *height > 0
+            && pixels.len() == *width as usize * *height as usize * 4 =>
+        {
+            let max_size = device.limits().max_texture_dimension_2d;
+            let factor = (*width).max(*height).div_ceil(max_size);
+            if factor > 1 {
+                let (width, height, pixels) = downsample(*width, *height, pixels, factor);
+                (width, height, Cow::Owned(pixels))
+            } else {
+                (*width, *height, Cow::Borrowed(pixels.as_slice()))
+            }
+        }
+        _ => (1, 1, Cow::Borrowed(&[0u8; 4][..])),
+    };
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("environment"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &pixels,
+    )
+}
+
+/// RGBA8 image averaged over blocks of `factor` x `factor` pixels, the last ones clipped to
+/// the image.
+fn downsample(width: u32, height: u32, pixels: &[u8], factor: u32) -> (u32, u32, Vec<u8>) {
+    let (small_width, small_height) = (width.div_ceil(factor), height.div_ceil(factor));
+    let mut small = Vec::with_capacity(small_width as usize * small_height as usize * 4);
+    for y in 0..small_height {
+        for x in 0..small_width {
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for source_y in y * factor..((y + 1) * factor).min(height) {
+                for source_x in x * factor..((x + 1) * factor).min(width) {
+                    let at = (source_y as usize * width as usize + source_x as usize) * 4;
+                    for (sum, &value) in sum.iter_mut().zip(&pixels[at..at + 4]) {
+                        *sum += value as u32;
+                    }
+                    count += 1;
+                }
+            }
+            small.extend(sum.map(|sum| ((sum + count / 2) / count) as u8));
+        }
+    }
+
+    (small_width, small_height, small)
+}
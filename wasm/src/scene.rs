@@ -0,0 +1,64 @@
+use crate::Transform;
+
+/// Splat asset placed in the scene, see [`Renderer::add_object`](crate::Renderer::add_object).
+#[derive(Clone, Debug)]
+pub struct SceneObject {
+    /// Object to scene, the scene transform is applied on top.
+    pub transform: Transform,
+    pub visible: bool,
+    /// Multiplies the opacity of every Gaussian of the object.
+    pub opacity: f32,
+}
+
+impl Default for SceneObject {
+    fn default() -> Self {
+        Self {
+            transform: Transform::default(),
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// `Object` of preprocess.wgsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct ObjectUniform {
+    /// object to world
+    matrix: [f32; 16],
+    /// columns padded to 4 floats
+    rotation: [[f32; 4]; 3],
+    scale: f32,
+    /// 0 when hidden
+    opacity: f32,
+    first_gaussian: u32,
+    first_splat: u32,
+}
+
+unsafe impl bytemuck::Zeroable for ObjectUniform {}
+unsafe impl bytemuck::Pod for ObjectUniform {}
+
+impl ObjectUniform {
+    pub fn new(
+        object: &SceneObject,
+        scene: &Transform,
+        first_gaussian: u64,
+        first_splat: u64,
+    ) -> Self {
+        let transform = scene * &object.transform;
+        let rotation = transform.rotation.to_rotation_matrix();
+        let column = |i: usize| {
+            let column = rotation.matrix().column(i);
+            [column.x, column.y, column.z, 0.0]
+        };
+
+        Self {
+            matrix: transform.matrix().as_slice().try_into().unwrap(),
+            rotation: [column(0), column(1), column(2)],
+            scale: transform.scale,
+            opacity: if object.visible { object.opacity } else { 0.0 },
+            first_gaussian: first_gaussian as u32,
+            first_splat: first_splat as u32,
+        }
+    }
+}
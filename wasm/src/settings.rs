@@ -0,0 +1,195 @@
+use wasm_bindgen::prelude::*;
+
+/// What the splats are composited over.
+#[derive(Clone, Debug)]
+pub enum Background {
+    /// Solid color with premultiplied alpha, transparent when alpha is 0.
+    Color([f32; 4]),
+    /// Equirectangular RGBA8 image looked up with the view direction of every pixel.
+    /// World -y is the top of the image, as in COLMAP captures.
+    Environment {
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    },
+}
+
+/// What the rasterizer draws, every mode but `Color` and `ShBand` is an opaque debug view
+/// that leaves the channel texture and the saturated tile count untouched.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Color = 0,
+    /// Heatmap of the splats in the list of every tile.
+    TileHeatmap = 1,
+    /// Heatmap of the splats blended into every pixel.
+    BlendCount = 2,
+    /// Heatmap of the expected depth.
+    Depth = 3,
+    /// Accumulated opacity in grayscale.
+    Opacity = 4,
+    /// Color of a single spherical harmonics band, see [`RenderSettings::sh_band`].
+    ShBand = 5,
+    /// Outlines of every splat at 1 and 3 standard deviations.
+    Ellipses = 6,
+}
+
+impl RenderMode {
+    /// Value at the top of the color map of the heatmap modes.
+    fn default_range(self) -> f32 {
+        match self {
+            RenderMode::TileHeatmap => 512.0,
+            RenderMode::BlendCount => 64.0,
+            RenderMode::Depth => 10.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    /// Apply the Mip-Splatting 2D filter, compensating opacity for the dilation.
+    pub mip_filter: bool,
+    /// Low pass filter added to the diagonal of every 2D covariance, in pixels.
+    pub dilation: f32,
+    /// Write accumulated opacity, expected depth and median depth of every pixel
+    /// to the channel texture and read them back after rendering.
+    pub output_channels: bool,
+    /// Record GPU timestamps around every stage. Needs a device created with
+    /// `TIMESTAMP_QUERY`, ignored otherwise.
+    pub profile: bool,
+    /// Gather [`crate::FrameStats`] on the GPU, read with `Renderer::read_stats`.
+    pub stats: bool,
+    pub mode: RenderMode,
+    /// Band from 0 to 3 drawn in [`RenderMode::ShBand`], offset by 0.5 like the full color.
+    pub sh_band: u32,
+    /// Splats per tile or pixel, or view depth, mapped to the top of the heatmaps.
+    pub debug_range: f32,
+    /// Largest projected radius in pixels of the nodes drawn from a [`crate::LodTree`],
+    /// coarser nodes replace finer ones up to it. 0 draws the full detail.
+    pub lod_threshold: f32,
+    #[wasm_bindgen(skip)]
+    pub background: Background,
+}
+
+#[wasm_bindgen]
+impl RenderSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settings matching models trained with Mip-Splatting.
+    pub fn mip_splatting() -> Self {
+        Self {
+            mip_filter: true,
+            dilation: 0.1,
+            ..Self::default()
+        }
+    }
+
+    /// Switches to `mode` with its default [`RenderSettings::debug_range`].
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+        self.debug_range = mode.default_range();
+    }
+
+    /// Composite over a solid color, `a` is straight (not premultiplied) alpha.
+    pub fn set_background_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.background = Background::Color([r * a, g * a, b * a, a]);
+    }
+
+    /// Leave the background transparent, the output is premultiplied alpha.
+    pub fn set_transparent_background(&mut self) {
+        self.background = Background::Color([0.0; 4]);
+    }
+
+    /// Composite over an equirectangular environment image of `width` x `height` RGBA8 pixels.
+    /// Fails and keeps the background when the image is empty or `pixels` has another size.
+    /// An image larger than the textures of the device is downsampled by the renderer.
+    pub fn set_environment(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    ) -> Result<(), JsValue> {
+        if width == 0 || height == 0 {
+            return Err(JsValue::from_str(&format!(
+                "environment of {width}x{height} pixels is empty"
+            )));
+        }
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(JsValue::from_str(&format!(
+                "environment of {width}x{height} RGBA8 pixels has {} bytes",
+                pixels.len()
+            )));
+        }
+        self.background = Background::Environment {
+            width,
+            height,
+            pixels,
+        };
+        Ok(())
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            mip_filter: false,
+            dilation: 0.3,
+            output_channels: false,
+            profile: false,
+            stats: false,
+            mode: RenderMode::Color,
+            sh_band: 0,
+            debug_range: 1.0,
+            lod_threshold: 0.0,
+            background: Background::Color([0.0, 0.0, 0.0, 1.0]),
+        }
+    }
+}
+
+/// `Settings` uniform of the shaders
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct SettingsUniform {
+    mip_filter: u32,
+    dilation: f32,
+    output_channels: u32,
+    environment: u32,
+    background: [f32; 4],
+    stats: u32,
+    mode: u32,
+    sh_band: u32,
+    debug_range: f32,
+    lod_threshold: f32,
+    _pad: [f32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for SettingsUniform {}
+unsafe impl bytemuck::Pod for SettingsUniform {}
+
+impl From<&RenderSettings> for SettingsUniform {
+    fn from(settings: &RenderSettings) -> Self {
+        let (environment, background) = match settings.background {
+            Background::Color(color) => (0, color),
+            Background::Environment { .. } => (1, [0.0, 0.0, 0.0, 1.0]),
+        };
+
+        Self {
+            mip_filter: settings.mip_filter as u32,
+            dilation: settings.dilation,
+            output_channels: settings.output_channels as u32,
+            environment,
+            background,
+            stats: settings.stats as u32,
+            mode: settings.mode as u32,
+            sh_band: settings.sh_band.min(3),
+            debug_range: settings.debug_range,
+            lod_threshold: settings.lod_threshold.max(0.0),
+            _pad: [0.0; 3],
+        }
+    }
+}
@@ -0,0 +1,232 @@
+struct Gaussian {
+    mean: vec3f,
+    norm: vec3f,
+    filter_3d: f32,
+    sh: array<vec3f, 16>,
+    scale: vec3f,
+    opacity: f32,
+    rotation: vec4f,
+}
+
+struct Object {
+    matrix: mat4x4f,
+    rotation: mat3x3f,
+    scale: f32,
+    opacity: f32,
+    firstGaussian: u32,
+    firstSplat: u32,
+}
+
+// world to volume space, where a box is [-1, 1]^3, a sphere the unit ball and a plane z >= 0
+struct Crop {
+    matrix: mat4x4f,
+    kind: u32,
+    invert: u32,
+}
+
+struct Edit {
+    // world to clip space of the camera of a rectangle or lasso
+    viewProj: mat4x4f,
+    crop: Crop,
+    // min and max corner in pixels
+    rect: vec4f,
+    // ranges of the opacity and the largest world space scale
+    opacity: vec2f,
+    scale: vec2f,
+    screen: vec2f,
+    selector: u32,
+    op: u32,
+    // rgb in [0, 1]
+    color: vec4f,
+    numSplat: u32,
+}
+
+// Gaussian state bits, also read by preprocess.wgsl
+const STATE_SELECTED = 1u;
+const STATE_HIDDEN = 2u;
+const STATE_DELETED = 4u;
+
+const SELECT_RECT = 0u;
+const SELECT_LASSO = 1u;
+const SELECT_CROP = 2u;
+const SELECT_OPACITY = 3u;
+const SELECT_SCALE = 4u;
+
+const OP_ADD = 0u;
+const OP_SUBTRACT = 1u;
+const OP_CLEAR = 2u;
+const OP_HIDE = 3u;
+const OP_SHOW = 4u;
+const OP_DELETE = 5u;
+const OP_RECOLOR = 6u;
+
+const CROP_BOX = 1u;
+const CROP_SPHERE = 2u;
+const CROP_PLANE = 3u;
+
+const SH_C0 = 0.28209479177387814f;
+
+@group(0) @binding(0) var<storage, read_write> gaussians: array<Gaussian>;
+@group(0) @binding(1) var<storage, read_write> state: array<atomic<u32>>;
+// sorted by firstSplat
+@group(0) @binding(2) var<storage, read> objects: array<Object>;
+@group(0) @binding(3) var<uniform> edit: Edit;
+// lasso polygon in pixels
+@group(0) @binding(4) var<storage, read> lasso: array<vec2f>;
+
+fn inside_crop(worldMean: vec3f) -> bool {
+    let crop = edit.crop;
+    let local = (crop.matrix * vec4f(worldMean, 1.0f)).xyz;
+
+    var inside = true;
+    switch(crop.kind) {
+        case CROP_BOX: {
+            inside = all(abs(local) <= vec3f(1.0f));
+        }
+        case CROP_SPHERE: {
+            inside = dot(local, local) <= 1.0f;
+        }
+        case CROP_PLANE: {
+            inside = local.z >= 0.0f;
+        }
+        default: {}
+    }
+
+    return inside != (crop.invert != 0u);
+}
+
+// even-odd rule
+fn inside_lasso(pixel: vec2f) -> bool {
+    let count = arrayLength(&lasso);
+    var inside = false;
+    var j = count - 1u;
+    for(var i = 0u; i < count; i++) {
+        let a = lasso[i];
+        let b = lasso[j];
+        if((a.y > pixel.y) != (b.y > pixel.y)
+            && pixel.x < (b.x - a.x) * (pixel.y - a.y) / (b.y - a.y) + a.x) {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    return inside;
+}
+
+// every instance of a Gaussian is tested, the Gaussian is selected if any of them passes
+@compute @workgroup_size(64)
+fn select_gaussians(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 64u + local_index;
+    if(index >= edit.numSplat) {
+        return;
+    }
+
+    // last object starting at or before this splat
+    var low = 0u;
+    var high = arrayLength(&objects);
+    while(high - low > 1u) {
+        let middle = (low + high) / 2u;
+        if(objects[middle].firstSplat <= index) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    let object = objects[low];
+    let gaussianIndex = object.firstGaussian + index - object.firstSplat;
+
+    // hidden objects and Gaussians can not be selected
+    if(object.opacity <= 0.0f
+        || (atomicLoad(&state[gaussianIndex]) & (STATE_HIDDEN | STATE_DELETED)) != 0u) {
+        return;
+    }
+
+    let gaussian = gaussians[gaussianIndex];
+    let worldMean = (object.matrix * vec4f(gaussian.mean, 1.0f)).xyz;
+
+    var selected = false;
+    switch(edit.selector) {
+        case SELECT_RECT, SELECT_LASSO: {
+            let clip = edit.viewProj * vec4f(worldMean, 1.0f);
+            if(clip.w > 0.0f) {
+                let ndc = clip.xy / clip.w;
+                let pixel = ((ndc + 1.0f) * edit.screen - 1.0f) * 0.5f;
+
+                if(edit.selector == SELECT_RECT) {
+                    selected = all(pixel >= edit.rect.xy) && all(pixel <= edit.rect.zw);
+                } else {
+                    selected = inside_lasso(pixel);
+                }
+            }
+        }
+        case SELECT_CROP: {
+            selected = inside_crop(worldMean);
+        }
+        case SELECT_OPACITY: {
+            selected = gaussian.opacity >= edit.opacity.x && gaussian.opacity <= edit.opacity.y;
+        }
+        case SELECT_SCALE: {
+            let scale = object.scale * max(gaussian.scale.x, max(gaussian.scale.y, gaussian.scale.z));
+            selected = scale >= edit.scale.x && scale <= edit.scale.y;
+        }
+        default: {}
+    }
+
+    if(!selected) {
+        return;
+    }
+
+    if(edit.op == OP_SUBTRACT) {
+        atomicAnd(&state[gaussianIndex], ~STATE_SELECTED);
+    } else {
+        atomicOr(&state[gaussianIndex], STATE_SELECTED);
+    }
+}
+
+// applied to every Gaussian, the ones outside the selection are left alone except by
+// OP_CLEAR and OP_SHOW
+@compute @workgroup_size(64)
+fn apply_edit(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 64u + local_index;
+    if(index >= arrayLength(&gaussians)) {
+        return;
+    }
+
+    let current = atomicLoad(&state[index]);
+    switch(edit.op) {
+        case OP_CLEAR: {
+            atomicStore(&state[index], current & ~STATE_SELECTED);
+        }
+        case OP_SHOW: {
+            atomicStore(&state[index], current & ~STATE_HIDDEN);
+        }
+        case OP_HIDE: {
+            if((current & STATE_SELECTED) != 0u) {
+                atomicStore(&state[index], (current | STATE_HIDDEN) & ~STATE_SELECTED);
+            }
+        }
+        case OP_DELETE: {
+            if((current & STATE_SELECTED) != 0u) {
+                atomicStore(&state[index], (current | STATE_DELETED) & ~STATE_SELECTED);
+            }
+        }
+        case OP_RECOLOR: {
+            if((current & STATE_SELECTED) != 0u) {
+                // flat color, the view dependent bands are dropped
+                gaussians[index].sh[0] = (edit.color.rgb - 0.5f) / SH_C0;
+                for(var i = 1u; i < 16u; i++) {
+                    gaussians[index].sh[i] = vec3f(0.0f);
+                }
+            }
+        }
+        default: {}
+    }
+}
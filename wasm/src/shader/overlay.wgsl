@@ -0,0 +1,29 @@
+struct Overlay {
+    // world to clip space
+    viewProj: mat4x4f,
+    screen: vec2f,
+    // premultiplied
+    color: vec4f,
+}
+
+struct Vertex {
+    @location(0) position: vec3f,
+    // screen space offset in pixels, for the point markers
+    @location(1) offset: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> overlay: Overlay;
+
+@vertex
+fn vert_main(vertex: Vertex) -> @builtin(position) vec4f {
+    var clip = overlay.viewProj * vec4f(vertex.position, 1.0);
+    clip = vec4f(clip.xy + vertex.offset * 2.0 / overlay.screen * clip.w, clip.zw);
+
+    // ndc y of the camera grows with the pixel rows, the framebuffer's the other way
+    return vec4f(clip.x, -clip.y, clip.zw);
+}
+
+@fragment
+fn frag_main() -> @location(0) vec4f {
+    return overlay.color;
+}
@@ -1,6 +1,8 @@
 struct Gaussian {
     mean: vec3f,
     norm: vec3f,
+    // mip-splatting 3d smoothing filter, 0 for regular models
+    filter_3d: f32,
     sh: array<vec3f, 16>,
     scale: vec3f,
     opacity: f32,
@@ -25,6 +27,85 @@ struct Splat {
     // color_clamped: vec3u,
 }
 
+struct Settings {
+    // apply the mip-splatting 2d filter with opacity compensation
+    mip_filter: u32,
+    // low pass filter added to the 2d covariance
+    dilation: f32,
+    // write alpha and depth to the channel texture
+    output_channels: u32,
+    // sample the environment map instead of the background color
+    environment: u32,
+    // premultiplied
+    background: vec4f,
+    // count saturated pixels for the frame stats
+    stats: u32,
+    // `RenderMode`, MODE_* below
+    mode: u32,
+    // only band of the sh evaluated in MODE_SH_BAND
+    sh_band: u32,
+    // value at the top of the debug color map
+    debug_range: f32,
+    // largest projected radius of the drawn LOD nodes in pixels
+    lod_threshold: f32,
+}
+
+struct Projection {
+    // `CameraModel`, MODEL_* below
+    model: u32,
+    // OpenCV fisheye k1 to k4
+    distortion: vec4f,
+}
+
+// splat asset placed in the scene, its splats are consecutive. Instances of an asset read
+// the same Gaussians.
+struct Object {
+    // model to world, uniform scale then rotation then translation
+    matrix: mat4x4f,
+    rotation: mat3x3f,
+    scale: f32,
+    // 0 when hidden
+    opacity: f32,
+    firstGaussian: u32,
+    firstSplat: u32,
+}
+
+// world to volume space, where a box is [-1, 1]^3, a sphere the unit ball and a plane z >= 0
+struct Crop {
+    matrix: mat4x4f,
+    kind: u32,
+    invert: u32,
+}
+
+// object space bounding spheres of a node of a LOD hierarchy, center and radius
+struct Lod {
+    // 0 for a leaf
+    sphere: vec4f,
+    // negative radius for a root
+    parent: vec4f,
+}
+
+// screen position and jacobian of the projection at a view space point
+struct Projected {
+    pixel: vec2f,
+    // rows of the jacobian as columns
+    jacobian: mat3x3f,
+}
+
+const MODE_SH_BAND = 5u;
+
+const CROP_NONE = 0u;
+const CROP_BOX = 1u;
+const CROP_SPHERE = 2u;
+const CROP_PLANE = 3u;
+
+const STATE_HIDDEN = 2u;
+const STATE_DELETED = 4u;
+
+const MODEL_PINHOLE = 0u;
+const MODEL_FISHEYE = 1u;
+const MODEL_EQUIRECTANGULAR = 2u;
+const MODEL_ORTHOGRAPHIC = 3u;
 
 @group(0) @binding(0) var<storage> gaussians: array<Gaussian>;
 @group(0) @binding(1) var<storage, read_write> splats: array<Splat>;
@@ -34,6 +115,19 @@ struct Splat {
 @group(0) @binding(5) var<uniform> tanFov: vec2f;
 @group(0) @binding(6) var<uniform> screen: vec2u;
 @group(0) @binding(7) var<uniform> camera: vec3f;
+@group(0) @binding(9) var<uniform> settings: Settings;
+// in pixels
+@group(0) @binding(14) var<uniform> principal: vec2f;
+@group(0) @binding(15) var<uniform> projection: Projection;
+
+// sorted by firstSplat
+@group(1) @binding(0) var<storage, read> objects: array<Object>;
+// a single CROP_NONE without crop volumes
+@group(1) @binding(1) var<storage, read> crops: array<Crop>;
+// STATE_* bits of edit.wgsl for every Gaussian
+@group(1) @binding(2) var<storage, read> state: array<u32>;
+// for every Gaussian, a root leaf outside of LOD hierarchies
+@group(1) @binding(3) var<storage, read> lods: array<Lod>;
 
 // @group(1) @binding(0) var<storage, read_write> keys: array<u32>;
 // @group(1) @binding(1) var<storage, read_write> values: array<u32>;
@@ -65,17 +159,179 @@ const  SH_C2 = array<f32, 5>(
 
  const far = f32(100.0);
 
+const PI = 3.14159265358979;
+
+// perspective divide through the projection matrix, jacobian of the EWA splatting paper
+fn project_pinhole(mean: vec3f, viewMean: vec3f) -> Projected {
+    let mean4 = projMat * vec4f(mean, 1.0f);
+    let w = 1.0f / (mean4.w + 0.0000001f);
+    let mean3 = vec3(mean4.x * w, mean4.y * w, mean4.z * w);
+
+    // focal * xy / z + principal, shifted so pixel centers sit on integer coordinates
+    let pixel = vec2f(
+        ((mean3.x + 1.0) * f32(screen.x) - 1.0) * 0.5,
+        ((mean3.y + 1.0) * f32(screen.y) - 1.0) * 0.5,
+    );
+
+    // the jacobian is only a good fit near the screen, clamp far off points towards it
+    let lim = tanFov * 1.3f;
+    let temp = viewMean.xy / viewMean.z;
+    let z = viewMean.z;
+    let x = min(lim.x, max(-lim.x, temp.x)) * z;
+    let y = min(lim.y, max(-lim.y, temp.y)) * z;
+
+    let J = mat3x3f(
+        focal.x / z, 0.0f, -(focal.x * x) / (z * z),
+		0.0f, focal.y / z, -(focal.y * y) / (z * z),
+		0, 0, 0
+    );
+
+    return Projected(pixel, J);
+}
+
+// OpenCV fisheye, the distorted angle to the optical axis is proportional to the distance
+// to the principal point
+fn project_fisheye(viewMean: vec3f) -> Projected {
+    let r = length(viewMean.xy);
+    let rho2 = dot(viewMean, viewMean);
+    let z = viewMean.z;
+
+    let theta = atan2(r, z);
+    let theta2 = theta * theta;
+    let k = projection.distortion;
+    let d = theta * (1.0 + theta2 * (k.x + theta2 * (k.y + theta2 * (k.z + theta2 * k.w))));
+    // derivative of d by theta
+    let dd = 1.0 + theta2 * (3.0 * k.x + theta2 * (5.0 * k.y + theta2 * (7.0 * k.z + theta2 * 9.0 * k.w)));
+
+    // on the optical axis the mapping is a pinhole with a focal length of dd(0)
+    if(r < 0.00001 * z) {
+        let pixel = focal * viewMean.xy / z + principal - 0.5;
+        let J = mat3x3f(
+            focal.x / z, 0.0f, -(focal.x * viewMean.x) / (z * z),
+            0.0f, focal.y / z, -(focal.y * viewMean.y) / (z * z),
+            0, 0, 0
+        );
+        return Projected(pixel, J);
+    }
+
+    let s = d / r;
+    let pixel = focal * viewMean.xy * s + principal - 0.5;
+
+    // derivative of s by x, divided by x, the same for y
+    let a = dd * z / (r * r * rho2) - d / (r * r * r);
+    let x = viewMean.x;
+    let y = viewMean.y;
+    let J = mat3x3f(
+        focal.x * (s + x * x * a), focal.x * x * y * a, -focal.x * x * dd / rho2,
+        focal.y * x * y * a, focal.y * (s + y * y * a), -focal.y * y * dd / rho2,
+        0, 0, 0
+    );
+
+    return Projected(pixel, J);
+}
+
+// parallel rays along z, the projection is affine and so is its jacobian
+fn project_orthographic(viewMean: vec3f) -> Projected {
+    let pixel = focal * viewMean.xy + principal - 0.5;
+    let J = mat3x3f(
+        focal.x, 0.0f, 0.0f,
+        0.0f, focal.y, 0.0f,
+        0, 0, 0
+    );
+
+    return Projected(pixel, J);
+}
+
+// full sphere, longitude along x and latitude along y with -y at the top
+fn project_equirectangular(viewMean: vec3f) -> Projected {
+    let x = viewMean.x;
+    let y = viewMean.y;
+    let z = viewMean.z;
+    // distance to the y axis, kept off the poles
+    let rho2 = dot(viewMean, viewMean);
+    let q2 = max(x * x + z * z, 0.00000001 * rho2);
+    let q = sqrt(q2);
+
+    let size = vec2f(screen);
+    let pixel = vec2f(
+        (atan2(x, z) / (2.0 * PI) + 0.5) * size.x,
+        (atan2(y, q) / PI + 0.5) * size.y,
+    ) - 0.5;
+
+    let longitude = size.x / (2.0 * PI);
+    let latitude = size.y / PI;
+    let J = mat3x3f(
+        longitude * z / q2, 0.0f, -longitude * x / q2,
+        -latitude * x * y / (rho2 * q), latitude * q / rho2, -latitude * z * y / (rho2 * q),
+        0, 0, 0
+    );
+
+    return Projected(pixel, J);
+}
+
+
+// whether a Gaussian at worldMean passes every crop volume
+fn inside_crops(worldMean: vec3f) -> bool {
+    for(var i = 0u; i < arrayLength(&crops); i++) {
+        let crop = crops[i];
+        let local = (crop.matrix * vec4f(worldMean, 1.0f)).xyz;
+
+        var inside = true;
+        switch(crop.kind) {
+            case CROP_BOX: {
+                inside = all(abs(local) <= vec3f(1.0f));
+            }
+            case CROP_SPHERE: {
+                inside = dot(local, local) <= 1.0f;
+            }
+            case CROP_PLANE: {
+                inside = local.z >= 0.0f;
+            }
+            default: {}
+        }
+
+        if(inside == (crop.invert != 0u)) {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+// upper bound of the radius in pixels of a bounding sphere of the object, 0 for a leaf
+fn projected_radius(object: Object, sphere: vec4f) -> f32 {
+    if(sphere.w <= 0.0f) {
+        return 0.0f;
+    }
+
+    let radius = object.scale * sphere.w;
+    let pixels = radius * max(focal.x, focal.y);
+    if(projection.model == MODEL_ORTHOGRAPHIC) {
+        return pixels;
+    }
+
+    let center = (object.matrix * vec4f(sphere.xyz, 1.0f)).xyz;
+    return pixels / max(distance(center, camera) - radius, 0.01f);
+}
+
+// the cut of the hierarchy: nodes small enough on screen whose parent is not. The spheres
+// are nested, so exactly one node is drawn on every path from a root to a leaf.
+fn in_lod_cut(object: Object, lod: Lod) -> bool {
+    return projected_radius(object, lod.sphere) <= settings.lod_threshold
+        && (lod.parent.w < 0.0f || projected_radius(object, lod.parent) > settings.lod_threshold);
+}
 
 @compute @workgroup_size(64)
 fn main(
     @builtin(workgroup_id) workgroup_id : vec3<u32>, 
     @builtin(num_workgroups) num_workgroups: vec3<u32>, 
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(local_invocation_index) local_index: u32,
     // @builtin(global_index) global_index: vec3<u32>
 ) {
-    let global_index = global_invocation_id.x;
+    // rows of workgroups past the dispatch limit of a dimension
+    let global_index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 64u + local_index;
 
-    if(global_index >= arrayLength(&gaussians)) {
+    if(global_index >= arrayLength(&splats)) {
         return;
     }
 
@@ -83,21 +339,48 @@ fn main(
     splats[global_index].radius = 0.0f;
     splats[global_index].tiles = 0u;
 
-    let gaussian = gaussians[global_index];
+    // last object starting at or before this splat
+    var low = 0u;
+    var high = arrayLength(&objects);
+    while(high - low > 1u) {
+        let middle = (low + high) / 2u;
+        if(objects[middle].firstSplat <= global_index) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    let object = objects[low];
+
+    if(object.opacity <= 0.0f) {
+        return;
+    }
+
+    let gaussianIndex = object.firstGaussian + global_index - object.firstSplat;
+    if((state[gaussianIndex] & (STATE_HIDDEN | STATE_DELETED)) != 0u
+        || !in_lod_cut(object, lods[gaussianIndex])) {
+        return;
+    }
+
+    let gaussian = gaussians[gaussianIndex];
     
     // TODO: view frustrum culling
 
 
-    let mean4 = projMat * vec4f(gaussian.mean, 1.0f);
-    // let mean4 =  projMat * vec4f(asd, 1.0f);
-    let w = 1.0f / (mean4.w + 0.0000001f);
-    let mean3 = vec3(mean4.x * w, mean4.y * w, mean4.z * w);
-
     // compute 3d covariance
+    // 3d smoothing filter, scale^2 + filter^2 with opacity compensation
+    let scale2 = gaussian.scale * gaussian.scale;
+    let filtered2 = scale2 + vec3f(gaussian.filter_3d * gaussian.filter_3d);
+    let filteredScale = sqrt(filtered2);
+    // per axis so small scales keep their precision, an axis of zero scale and no filter is
+    // left as is instead of giving 0 / 0
+    let ratio = select(vec3f(1.0f), scale2 / filtered2, filtered2 > vec3f(0.0f));
+    var opacity = object.opacity * gaussian.opacity * sqrt(ratio.x * ratio.y * ratio.z);
+
     var scaleMat = mat3x3f();
-    scaleMat[0][0] = gaussian.scale.x;
-    scaleMat[1][1] = gaussian.scale.y;
-    scaleMat[2][2] = gaussian.scale.z;
+    scaleMat[0][0] = filteredScale.x;
+    scaleMat[1][1] = filteredScale.y;
+    scaleMat[2][2] = filteredScale.z;
 
 
     let r = gaussian.rotation.x;
@@ -113,32 +396,53 @@ fn main(
     let M = scaleMat * rotMat;
     
 
-    let sigma3 = transpose(M) * M;
+    // rotated and scaled into world space with its object
+    let sigma3 = object.scale * object.scale * object.rotation * (transpose(M) * M) * transpose(object.rotation);
     let cov3 = array<f32, 6>(
         sigma3[0][0], sigma3[0][1], sigma3[0][2], 
         sigma3[1][1], sigma3[1][2], sigma3[2][2]
     );
     
     // splat to 2d covariance
-    let viewMean = viewMat * vec4f(gaussian.mean, 1.0f);
-    var mean = viewMean;
+    let worldMean = (object.matrix * vec4f(gaussian.mean, 1.0f)).xyz;
+    if(!inside_crops(worldMean)) {
+        return;
+    }
+    let viewMean = viewMat * vec4f(worldMean, 1.0f);
+
+    // along the optical axis for pinhole and orthographic cameras, the distance to the camera
+    // for the models seeing behind it. Orthographic depth is not divided by anything, the keys
+    // stay ordered as long as the scene is in front of the camera.
+    var depth = viewMean.z;
+    if(projection.model == MODEL_FISHEYE || projection.model == MODEL_EQUIRECTANGULAR) {
+        depth = length(viewMean.xyz);
+    }
 
     // viewFrustrum culling
-    if(viewMean.z <= 0.01 || viewMean.z > 1000.0) {
+    if(depth <= 0.01 || depth > 1000.0) {
         return;
     }
-    
-    let lim = tanFov * 1.3f;
-    let temp = mean.xy / mean.z;
 
-    mean.x = min(lim.x, max(-lim.x, temp.x)) * mean.z;
-    mean.y = min(lim.y, max(-lim.y, temp.y)) * mean.z;
-
-    let J = mat3x3f(
-        focal.x / mean.z, 0.0f, -(focal.x * mean.x) / (mean.z * mean.z),
-		0.0f, focal.y / mean.z, -(focal.y * mean.y) / (mean.z * mean.z),
-		0, 0, 0
-    );
+    var projected: Projected;
+    switch projection.model {
+        case MODEL_FISHEYE: {
+            // the distortion polynomial folds over towards the back of the camera
+            if(atan2(length(viewMean.xy), viewMean.z) > 0.95 * PI) {
+                return;
+            }
+            projected = project_fisheye(viewMean.xyz);
+        }
+        case MODEL_EQUIRECTANGULAR: {
+            projected = project_equirectangular(viewMean.xyz);
+        }
+        case MODEL_ORTHOGRAPHIC: {
+            projected = project_orthographic(viewMean.xyz);
+        }
+        default: {
+            projected = project_pinhole(worldMean, viewMean.xyz);
+        }
+    }
+    let J = projected.jacobian;
 
     let W = mat3x3f(
         viewMat[0][0], viewMat[1][0], viewMat[2][0],
@@ -155,12 +459,16 @@ fn main(
     );
 
     let sigma2 = transpose(T) * VrK * T;
-    let cov2 = vec3f(
-        sigma2[0][0] + 0.3f, 
+    var cov2 = vec3f(
+        sigma2[0][0], 
         sigma2[0][1], 
-        sigma2[1][1] + 0.3f
+        sigma2[1][1]
     );
 
+    let unfilteredDet = cov2.x * cov2.z - cov2.y * cov2.y;
+    cov2.x += settings.dilation;
+    cov2.z += settings.dilation;
+
     let determinant = cov2.x * cov2.z - cov2.y * cov2.y;
     if(determinant == 0.0f) {
         return;
@@ -168,6 +476,11 @@ fn main(
 
     let conic = vec3f(cov2.z, -cov2.y, cov2.x) / determinant;
 
+    // 2d mip filter, keep the energy of the splat the same after the dilation
+    if(settings.mip_filter != 0u) {
+        opacity *= sqrt(max(0.000025f, unfilteredDet / determinant));
+    }
+
     // find tile
     let mid = 0.5f * (cov2.x + cov2.z);
     let lambda = vec2f(
@@ -175,24 +488,47 @@ fn main(
         mid - sqrt(max(0.1f, mid * mid - determinant)),
     );
     let radius = ceil(3.0f * sqrt(max(lambda.x, lambda.y)));
-    let pixel = vec2f(
-        ((mean3.x + 1.0) * f32(screen.x) - 1.0) * 0.5,
-        ((mean3.y + 1.0) * f32(screen.y) - 1.0) * 0.5,
-    );
+    let pixel = projected.pixel;
 
     let tileRange = vec2u(
         (screen.x + TILE.x - 1) / TILE.x,
         (screen.y + TILE.y - 1) / TILE.y,
     );
 
-    let minTile = vec2u(
-        min(tileRange.x, max(0u, u32(pixel.x - radius) / TILE.x)),
-        min(tileRange.y, max(0u, u32(pixel.y - radius) / TILE.y))
-    );
-    let maxTile = vec2u(
-        min(tileRange.x, max(0u, (u32(pixel.x + radius) + TILE.x - 1) / TILE.x)),
-        min(tileRange.y, max(0u, (u32(pixel.y + radius) + TILE.y - 1) / TILE.y))
-    );
+    // axis aligned extent of the 3 sigma ellipse, tighter than the circle of `radius`
+    let extent = ceil(3.0f * sqrt(vec2f(cov2.x, cov2.z)));
+
+    // clamp in float before converting, so splats hanging off the left/top edge
+    // do not wrap around when cast to u32
+    let tileSize = vec2f(TILE);
+    var minTile = vec2u(clamp(
+        floor((pixel - extent) / tileSize),
+        vec2f(0.0f),
+        vec2f(tileRange)
+    ));
+    var maxTile = vec2u(clamp(
+        floor((pixel + extent) / tileSize) + 1.0f,
+        vec2f(0.0f),
+        vec2f(tileRange)
+    ));
+
+    // longitude wraps around, splats crossing the seam continue on the other side with the
+    // columns past the last one taken modulo when writing the keys. The last column can be
+    // cut by the screen, which shifts the wrapped columns by up to one tile, so they get one
+    // tile of margin.
+    if(projection.model == MODEL_EQUIRECTANGULAR) {
+        let columns = f32(tileRange.x);
+        var first = floor((pixel.x - extent.x) / tileSize.x);
+        var last = floor((pixel.x + extent.x) / tileSize.x) + 1.0f;
+        if(first < 0.0f) {
+            first += columns - 1.0f;
+            last += columns;
+        } else if(last > columns) {
+            last += 1.0f;
+        }
+        minTile.x = u32(first);
+        maxTile.x = u32(min(last, first + columns));
+    }
 
     let touched = (maxTile.x - minTile.x) * (maxTile.y - minTile.y);
     if(touched == 0) {
@@ -200,8 +536,19 @@ fn main(
     }
 
     // sh to rgb
-    let dir = normalize(gaussian.mean - camera);
-    var rgb = SH_C0 * gaussian.sh[0];
+    var band = vec4f(1.0);
+    if(settings.mode == MODE_SH_BAND) {
+        band = select(vec4f(0.0), vec4f(1.0), vec4u(0u, 1u, 2u, 3u) == vec4u(settings.sh_band));
+    }
+
+    // every ray of an orthographic camera points along its z axis
+    var worldDir = normalize(worldMean - camera);
+    if(projection.model == MODEL_ORTHOGRAPHIC) {
+        worldDir = normalize(vec3f(viewMat[0][2], viewMat[1][2], viewMat[2][2]));
+    }
+    // evaluating the sh in model space is the same as rotating their coefficients with the scene
+    let dir = transpose(object.rotation) * worldDir;
+    var rgb = band.x * SH_C0 * gaussian.sh[0];
 
     {
         let x = dir.x; 
@@ -209,10 +556,10 @@ fn main(
         let z = dir.z;
 
         rgb = 
-            rgb -
-            SH_C1 * dir.y * gaussian.sh[1] +
+            rgb + band.y * (
+            -SH_C1 * dir.y * gaussian.sh[1] +
             SH_C1 * dir.z * gaussian.sh[2] -
-            SH_C1 * dir.x * gaussian.sh[3];
+            SH_C1 * dir.x * gaussian.sh[3]);
 
         let xx = x * x;
         let yy = y * y;
@@ -221,21 +568,21 @@ fn main(
         let yz = y * z;
         let xz = x * z;
 
-        rgb = rgb +
+        rgb = rgb + band.z * (
 				SH_C2[0] * xy * gaussian.sh[4] +
 				SH_C2[1] * yz * gaussian.sh[5] +
 				SH_C2[2] * (2.0f * zz - xx - yy) * gaussian.sh[6] +
 				SH_C2[3] * xz * gaussian.sh[7] +
-				SH_C2[4] * (xx - yy) * gaussian.sh[8];
+				SH_C2[4] * (xx - yy) * gaussian.sh[8]);
 
-        rgb = rgb +
+        rgb = rgb + band.w * (
 					SH_C3[0] * y * (3.0f * xx - yy) * gaussian.sh[9] +
 					SH_C3[1] * xy * z * gaussian.sh[10] +
 					SH_C3[2] * y * (4.0f * zz - xx - yy) * gaussian.sh[11] +
 					SH_C3[3] * z * (2.0f * zz - 3.0f * xx - 3.0f * yy) * gaussian.sh[12] +
 					SH_C3[4] * x * (4.0f * zz - xx - yy) * gaussian.sh[13] +
 					SH_C3[5] * z * (xx - yy) * gaussian.sh[14] +
-					SH_C3[6] * x * (xx - 3.0f * yy) * gaussian.sh[15];
+					SH_C3[6] * x * (xx - 3.0f * yy) * gaussian.sh[15]);
     }
 
     rgb += 0.5;
@@ -257,11 +604,11 @@ fn main(
     splats[global_index].color = rgb;
     // splats[global_index].color_clamped = clamped;
     splats[global_index].cov = conic;
-    splats[global_index].opacity = gaussian.opacity;
+    splats[global_index].opacity = opacity;
     
     splats[global_index].tiles = touched;
     // splats[global_index].tiles = 3u;
-    splats[global_index].depth = viewMean.z;
+    splats[global_index].depth = depth;
 
     splats[global_index].min = minTile;
     splats[global_index].max = maxTile;
@@ -15,15 +15,199 @@ struct Splat {
 
     // color_clamped: vec3u,
 }
+
+struct Settings {
+    mip_filter: u32,
+    dilation: f32,
+    output_channels: u32,
+    environment: u32,
+    background: vec4f,
+    stats: u32,
+    mode: u32,
+    sh_band: u32,
+    debug_range: f32,
+}
+
+struct FrameStats {
+    visible: u32,
+    keys: u32,
+    maxPerTile: u32,
+    occupiedTiles: u32,
+    saturatedTiles: u32,
+    saturatedPixels: array<atomic<u32>>,
+}
+
+struct DepthTest {
+    // third and fourth row of the projection the depth was written with: P22, P23, P32, P33
+    projection: vec4f,
+    enabled: u32,
+}
+
+struct Projection {
+    model: u32,
+    distortion: vec4f,
+}
+
+struct Pick {
+    // splat with the largest blending weight, NO_SPLAT without any
+    splat: u32,
+    weight: f32,
+    // accumulated opacity and expected depth
+    opacity: f32,
+    depth: f32,
+    // at the expected depth along the pixel ray
+    position: vec3f,
+}
+
 const TILE = vec2u(8, 8);
-const BG = vec3f(0.0);
+const PI = 3.14159265358979;
+const NO_SPLAT = 0xffffffffu;
+
+// `CameraModel`
+const MODEL_FISHEYE = 1u;
+const MODEL_EQUIRECTANGULAR = 2u;
+const MODEL_ORTHOGRAPHIC = 3u;
+
+// `RenderMode` drawn by `debug`
+const MODE_TILE_HEATMAP = 1u;
+const MODE_BLEND_COUNT = 2u;
+const MODE_DEPTH = 3u;
+const MODE_OPACITY = 4u;
+const MODE_ELLIPSES = 6u;
 
 @group(0) @binding(1) var<storage, read_write> splats: array<Splat>;
+@group(0) @binding(2) var<uniform> viewMat: mat4x4f;
+@group(0) @binding(4) var<uniform> focal: vec2f;
 @group(0) @binding(6) var<uniform> screen: vec2u;
-@group(0) @binding(8) var<storage, read_write> out: array<vec3f>;
+@group(0) @binding(8) var<storage, read_write> out: array<vec4f>;
+@group(0) @binding(9) var<uniform> settings: Settings;
+// accumulated opacity, expected depth, median depth
+@group(0) @binding(10) var channels: texture_storage_2d<rgba32float, write>;
+@group(0) @binding(11) var environment: texture_2d<f32>;
+@group(0) @binding(12) var environmentSampler: sampler;
+// top left pixel of the view in the target and its depth buffer
+@group(0) @binding(13) var<uniform> origin: vec2u;
+// in pixels
+@group(0) @binding(14) var<uniform> principal: vec2f;
+@group(0) @binding(15) var<uniform> projection: Projection;
+
+// sorted splat indices and the range of every tile in them
+@group(1) @binding(0) var<storage, read> values: array<u32>;
+@group(1) @binding(1) var<storage, read> range: array<vec2u>;
+@group(1) @binding(2) var<storage, read_write> stats: FrameStats;
+
+// depth buffer of opaque geometry drawn by the host
+@group(2) @binding(0) var opaqueDepth: texture_depth_2d;
+@group(2) @binding(1) var<uniform> depthTest: DepthTest;
+
+// written by `pick` only
+@group(3) @binding(0) var<uniform> pickPixel: vec2u;
+@group(3) @binding(1) var<storage, read_write> picked: Pick;
+
+// view space distance of the opaque surface, infinite without a depth buffer
+fn opaque_distance(pixel: vec2u) -> f32 {
+    if(depthTest.enabled == 0u) {
+        return bitcast<f32>(0x7f800000u);
+    }
+
+    let d = textureLoad(opaqueDepth, origin + pixel, 0);
+    let p = depthTest.projection;
+    let z = (p.y - d * p.w) / (d * p.z - p.x);
 
-@group(1) @binding(1) var<storage, read_write> values: array<u32>;
-@group(1) @binding(4) var<storage, read_write> range: array<vec2u>;
+    return abs(z);
+}
+
+// from the splat to the pixel center, across the seam of equirectangular views if shorter
+fn splat_offset(mean: vec2f, pixel: vec2u) -> vec2f {
+    var offset = mean - vec2f(pixel);
+    if(projection.model == MODEL_EQUIRECTANGULAR) {
+        let width = f32(screen.x);
+        offset.x -= width * round(offset.x / width);
+    }
+
+    return offset;
+}
+
+// view space ray through the pixel center, inverse of the projection in preprocess
+fn pixel_ray(pixel: vec2u) -> vec3f {
+    let center = vec2f(pixel) + 0.5;
+
+    switch projection.model {
+        case MODEL_FISHEYE: {
+            let m = (center - principal) / focal;
+            let d = length(m);
+            if(d == 0.0) {
+                return vec3f(0.0, 0.0, 1.0);
+            }
+
+            // newton steps on the distortion polynomial
+            let k = projection.distortion;
+            var theta = d;
+            for(var i = 0; i < 8; i++) {
+                let theta2 = theta * theta;
+                let f = theta * (1.0 + theta2 * (k.x + theta2 * (k.y + theta2 * (k.z + theta2 * k.w)))) - d;
+                let df = 1.0 + theta2 * (3.0 * k.x + theta2 * (5.0 * k.y + theta2 * (7.0 * k.z + theta2 * 9.0 * k.w)));
+                theta -= f / df;
+            }
+
+            return vec3f(sin(theta) * m / d, cos(theta));
+        }
+        case MODEL_EQUIRECTANGULAR: {
+            let longitude = (center.x / f32(screen.x) - 0.5) * 2.0 * PI;
+            let latitude = (center.y / f32(screen.y) - 0.5) * PI;
+
+            return vec3f(
+                cos(latitude) * sin(longitude),
+                sin(latitude),
+                cos(latitude) * cos(longitude)
+            );
+        }
+        case MODEL_ORTHOGRAPHIC: {
+            return vec3f(0.0, 0.0, 1.0);
+        }
+        default: {
+            return vec3f((center - principal) / focal, 1.0);
+        }
+    }
+}
+
+fn background(pixel: vec2u) -> vec4f {
+    if(settings.environment == 0u) {
+        return settings.background;
+    }
+
+    let viewDir = pixel_ray(pixel);
+    let rotation = mat3x3f(viewMat[0].xyz, viewMat[1].xyz, viewMat[2].xyz);
+    let dir = normalize(transpose(rotation) * viewDir);
+
+    // equirectangular, world -y at the top
+    let uv = vec2f(
+        atan2(dir.x, dir.z) / (2.0 * PI) + 0.5,
+        acos(clamp(-dir.y, -1.0, 1.0)) / PI
+    );
+
+    return vec4f(textureSampleLevel(environment, environmentSampler, uv, 0.0).rgb, 1.0);
+}
+
+// polynomial fit of the turbo color map, x in [0, 1]
+fn turbo(value: f32) -> vec3f {
+    let red4 = vec4f(0.13572138, 4.61539260, -42.66032258, 132.13108234);
+    let green4 = vec4f(0.09140261, 2.19418839, 4.84296658, -14.18503333);
+    let blue4 = vec4f(0.10667330, 12.64194608, -60.58204836, 110.36276771);
+    let red2 = vec2f(-152.94239396, 59.28637943);
+    let green2 = vec2f(4.27729857, 2.82956604);
+    let blue2 = vec2f(-89.90310912, 27.34824973);
+
+    let x = clamp(value, 0.0, 1.0);
+    let v4 = vec4f(1.0, x, x * x, x * x * x);
+    let v2 = v4.zw * v4.z;
+
+    return vec3f(
+        dot(v4, red4) + dot(v2, red2),
+        dot(v4, green4) + dot(v2, green2),
+        dot(v4, blue4) + dot(v2, blue2)
+    );
+}
 
 @compute @workgroup_size(8, 8)
 fn main(
@@ -45,12 +229,23 @@ fn main(
 
     var t = f32(1.0);
     var color = vec3f(0.0);
+    var depth = f32(0.0);
+    var medianDepth = f32(0.0);
+
+    var saturated = false;
+
+    let opaque = opaque_distance(pixel);
 
     for(var i=tileRange.x; i < tileRange.y; i++) {
         let index = values[i];
         let splat = splats[index];
 
-        let distance = splat.mean.xy - vec2f(pixel);
+        // splats are sorted front to back, everything after is hidden by the surface
+        if(splat.depth > opaque) {
+            break;
+        }
+
+        let distance = splat_offset(splat.mean, pixel);
 
         let power = 
             -0.5f * 
@@ -71,16 +266,224 @@ fn main(
         }
         let test_t = t * (1.0 - alpha);
         if(test_t < 0.0001) {
+            saturated = true;
             break;
         }
 
         color = color + (splat.color * alpha * t);
+        depth = depth + (splat.depth * alpha * t);
+
+        // first splat to bring the transmittance below one half
+        if(t > 0.5 && test_t <= 0.5) {
+            medianDepth = splat.depth;
+        }
         t = test_t;
     }
 
 
-    out[pixel_id] = color + t * BG;
+    if(settings.stats != 0u && saturated) {
+        atomicAdd(&stats.saturatedPixels[tile], 1u);
+    }
+
+    // the host draws its own background behind opaque geometry
+    var bg = vec4f(0.0);
+    if(depthTest.enabled == 0u) {
+        bg = background(pixel);
+    }
+    out[pixel_id] = vec4f(color + t * bg.rgb, 1.0 - t + t * bg.a);
+
+    if(settings.output_channels != 0u) {
+        let opacity = 1.0 - t;
+
+        var expectedDepth = f32(0.0);
+        if(opacity > 0.0) {
+            expectedDepth = depth / opacity;
+        }
+
+        textureStore(channels, pixel, vec4f(opacity, expectedDepth, medianDepth, 0.0));
+    }
     
 
     return;
-}
\ No newline at end of file
+}
+
+// heatmaps and outlines of the debug render modes, opaque
+@compute @workgroup_size(8, 8)
+fn debug(
+    @builtin(workgroup_id) workgroup_id : vec3<u32>, 
+    @builtin(num_workgroups) num_workgroups: vec3<u32>, 
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let pixel = global_invocation_id.xy;
+    if(pixel.x >= screen.x || pixel.y >= screen.y) {
+        return;
+    }
+
+    let pixel_id = pixel.y * screen.x + pixel.x;
+
+    let tile = workgroup_id.y * num_workgroups.x + workgroup_id.x;
+    let tileRange = range[tile];
+
+    if(settings.mode == MODE_TILE_HEATMAP) {
+        let count = f32(tileRange.y - tileRange.x);
+        out[pixel_id] = vec4f(turbo(count / settings.debug_range), 1.0);
+        return;
+    }
+
+    var t = f32(1.0);
+    var depth = f32(0.0);
+    var count = 0u;
+    var outline = vec3f(0.0);
+
+    let opaque = opaque_distance(pixel);
+
+    for(var i=tileRange.x; i < tileRange.y; i++) {
+        let splat = splats[values[i]];
+
+        if(splat.depth > opaque) {
+            break;
+        }
+
+        let distance = splat_offset(splat.mean, pixel);
+
+        let power = 
+            -0.5f * 
+            (
+                splat.cov.x * distance.x * distance.x +
+                splat.cov.z * distance.y * distance.y
+            )
+            - splat.cov.y * distance.x * distance.y;
+
+        if(settings.mode == MODE_ELLIPSES) {
+            // mahalanobis distance and how much it changes over a pixel
+            let d = sqrt(max(-2.0 * power, 0.0));
+            let gradient = length(vec2f(
+                splat.cov.x * distance.x + splat.cov.y * distance.y,
+                splat.cov.y * distance.x + splat.cov.z * distance.y
+            )) / max(d, 0.000001);
+
+            var alpha = f32(0.0);
+            if(abs(d - 1.0) < gradient) {
+                alpha = splat.opacity;
+            } else if(abs(d - 3.0) < gradient) {
+                alpha = 0.5 * splat.opacity;
+            }
+            alpha = min(0.99, alpha);
+
+            outline = outline + splat.color * alpha * t;
+            t = t * (1.0 - alpha);
+            if(t < 0.0001) {
+                break;
+            }
+            continue;
+        }
+
+        if(power > 0.0) {
+            continue;
+        }
+
+        let alpha = min(0.99, splat.opacity * exp(power));
+        if (alpha < (1.0 / 255.0)) {
+			continue;
+        }
+        let test_t = t * (1.0 - alpha);
+        if(test_t < 0.0001) {
+            break;
+        }
+
+        depth = depth + (splat.depth * alpha * t);
+        count++;
+        t = test_t;
+    }
+
+    var value = outline;
+    switch(settings.mode) {
+        case MODE_BLEND_COUNT: {
+            value = turbo(f32(count) / settings.debug_range);
+        }
+        case MODE_DEPTH: {
+            value = vec3f(0.0);
+            if(t < 1.0) {
+                value = turbo(depth / (1.0 - t) / settings.debug_range);
+            }
+        }
+        case MODE_OPACITY: {
+            value = vec3f(1.0 - t);
+        }
+        default: {}
+    }
+
+    out[pixel_id] = vec4f(value, 1.0);
+}
+
+// dominant splat and expected surface under pickPixel, blended like `main`
+@compute @workgroup_size(1)
+fn pick() {
+    let pixel = pickPixel;
+
+    var result = Pick(NO_SPLAT, 0.0, 0.0, 0.0, vec3f(0.0));
+    if(pixel.x >= screen.x || pixel.y >= screen.y) {
+        picked = result;
+        return;
+    }
+
+    let tileXY = pixel / TILE;
+    let tile = tileXY.y * ((screen.x + TILE.x - 1u) / TILE.x) + tileXY.x;
+    let tileRange = range[tile];
+
+    var t = f32(1.0);
+    var depth = f32(0.0);
+
+    for(var i = tileRange.x; i < tileRange.y; i++) {
+        let index = values[i];
+        let splat = splats[index];
+
+        let distance = splat_offset(splat.mean, pixel);
+        let power =
+            -0.5f *
+            (
+                splat.cov.x * distance.x * distance.x +
+                splat.cov.z * distance.y * distance.y
+            )
+            - splat.cov.y * distance.x * distance.y;
+
+        if(power > 0.0) {
+            continue;
+        }
+
+        let alpha = min(0.99, splat.opacity * exp(power));
+        if (alpha < (1.0 / 255.0)) {
+            continue;
+        }
+        let test_t = t * (1.0 - alpha);
+        if(test_t < 0.0001) {
+            break;
+        }
+
+        let weight = alpha * t;
+        if(weight > result.weight) {
+            result.splat = index;
+            result.weight = weight;
+        }
+        depth = depth + splat.depth * weight;
+        t = test_t;
+    }
+
+    result.opacity = 1.0 - t;
+    if(result.opacity > 0.0) {
+        result.depth = depth / result.opacity;
+    }
+
+    // depth is along the optical axis for pinhole and orthographic cameras, along the ray
+    // for the others
+    let center = vec2f(pixel) + 0.5;
+    var point = pixel_ray(pixel) * result.depth;
+    if(projection.model == MODEL_ORTHOGRAPHIC) {
+        point = vec3f((center - principal) / focal, result.depth);
+    }
+
+    let rotation = mat3x3f(viewMat[0].xyz, viewMat[1].xyz, viewMat[2].xyz);
+    result.position = transpose(rotation) * (point - viewMat[3].xyz);
+
+    picked = result;
+}
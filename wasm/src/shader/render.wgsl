@@ -1,5 +1,6 @@
 @group(0) @binding(6) var<uniform> screen: vec2u;
-@group(0) @binding(8) var<storage, read_write> out: array<vec3f>;
+@group(0) @binding(8) var<storage, read_write> out: array<vec4f>;
+@group(0) @binding(13) var<uniform> origin: vec2u;
 
 
 @vertex
@@ -18,7 +19,9 @@ fn vert_main(@builtin(vertex_index)index : u32) -> @builtin(position) vec4f {
 
 @fragment
 fn frag_main(@builtin(position) pos: vec4f) -> @location(0) vec4f {
-    let index = u32(pos.y) * screen.x + u32(pos.x);
+    // the viewport of the view starts at its origin
+    let pixel = vec2u(pos.xy) - origin;
+    let index = pixel.y * screen.x + pixel.x;
 
-    return vec4f(out[index], 1);
+    return out[index];
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+struct Splat {
+    mean: vec2f,
+    radius: f32,
+    depth: f32,
+
+    cov: vec3f,
+    tiles: u32,
+
+    color: vec3f,
+    opacity: f32,
+
+
+    min: vec2u,
+    max: vec2u
+}
+
+struct FrameStats {
+    visible: atomic<u32>,
+    // copied from the sort size
+    keys: u32,
+    maxPerTile: atomic<u32>,
+    occupiedTiles: atomic<u32>,
+    saturatedTiles: atomic<u32>,
+    // pixels of every tile that reached full opacity, written by rasterize
+    saturatedPixels: array<u32>,
+}
+
+const TILE = vec2u(8, 8);
+
+@group(0) @binding(0) var<storage, read> splats: array<Splat>;
+@group(0) @binding(1) var<storage, read> range: array<vec2u>;
+@group(0) @binding(2) var<uniform> screen: vec2u;
+@group(0) @binding(3) var<storage, read_write> stats: FrameStats;
+
+@compute @workgroup_size(64)
+fn count_visible(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 64u + local_index;
+
+    if(index >= arrayLength(&splats)) {
+        return;
+    }
+
+    if(splats[index].tiles > 0u) {
+        atomicAdd(&stats.visible, 1u);
+    }
+}
+
+@compute @workgroup_size(64)
+fn tile_stats(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let tile = global_invocation_id.x;
+
+    let tileRange = (screen + TILE - 1u) / TILE;
+    if(tile >= tileRange.x * tileRange.y) {
+        return;
+    }
+
+    let tileSplats = range[tile].y - range[tile].x;
+    if(tileSplats == 0u) {
+        return;
+    }
+
+    atomicMax(&stats.maxPerTile, tileSplats);
+    atomicAdd(&stats.occupiedTiles, 1u);
+
+    // tiles on the right and bottom edge are cut by the screen
+    let origin = vec2u(tile % tileRange.x, tile / tileRange.x) * TILE;
+    let size = min(TILE, screen - origin);
+    if(stats.saturatedPixels[tile] == size.x * size.y) {
+        atomicAdd(&stats.saturatedTiles, 1u);
+    }
+}
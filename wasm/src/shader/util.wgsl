@@ -43,9 +43,10 @@ var<workgroup> section: array<u32, 128>;
 @compute @workgroup_size(64)
 fn compute_prefix_sum(
     @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
     @builtin(local_invocation_index) local_index: u32,
 ) {
-  let index = workgroup_id.x * 128 + local_index;
+  let index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 128 + local_index;
   let n = arrayLength(&splats);
 
   if(index < n) {
@@ -142,9 +143,11 @@ fn finish_prefix_sum(@builtin(local_invocation_index) local_index: u32) {
 
 @compute @workgroup_size(64)
 fn copy_key_value(
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
 ) {
-    let index = global_invocation_id.x;
+    let index = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * 64u + local_index;
 
     if(index >= arrayLength(&splats)) {
         return;
@@ -165,7 +168,8 @@ fn copy_key_value(
     var i = 0u;
     for(var x = splat.min.x; x < splat.max.x; x++) {
         for(var y = splat.min.y; y < splat.max.y; y++) {
-            let tileId = y * num_tile + x;
+            // columns of equirectangular splats wrap around
+            let tileId = y * num_tile + x % num_tile;
             let key = (tileId << 16) + depth;
 
             keys[offset + i] = key;
@@ -0,0 +1,86 @@
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+/// Bytes of the `FrameStats` header of stats.wgsl, followed by a u32 per tile.
+pub(crate) const STATS_HEADER_SIZE: u64 = 5 * 4;
+
+/// Offset of `FrameStats.keys`, the sort size is copied there.
+pub(crate) const STATS_KEYS_OFFSET: u64 = 4;
+
+/// Counters gathered on the GPU while rendering a frame.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStats {
+    /// Gaussians touching at least one tile.
+    pub visible_gaussians: u32,
+    /// Gaussians behind the camera, outside the screen or degenerate.
+    pub culled_gaussians: u32,
+    /// Tile keys sorted, a Gaussian is duplicated for every tile it touches.
+    pub duplicated_keys: u32,
+    pub max_splats_per_tile: u32,
+    /// Over the tiles touched by at least one splat.
+    pub mean_splats_per_tile: f32,
+    /// Tiles touched by at least one splat.
+    pub occupied_tiles: u32,
+    /// Tiles where every pixel reached full opacity before the end of its splat list.
+    pub saturated_tiles: u32,
+}
+
+impl FrameStats {
+    /// From the `FrameStats` header of stats.wgsl of a frame drawing `num_splat` splats.
+    pub(crate) fn from_header(bytes: &[u8], num_splat: u64) -> Self {
+        let header: &[u32] = bytemuck::cast_slice(bytes);
+        let [visible, keys, max_per_tile, occupied, saturated] = header.try_into().unwrap();
+
+        Self {
+            visible_gaussians: visible,
+            culled_gaussians: num_splat as u32 - visible,
+            duplicated_keys: keys,
+            max_splats_per_tile: max_per_tile,
+            mean_splats_per_tile: if occupied > 0 {
+                keys as f32 / occupied as f32
+            } else {
+                0.0
+            },
+            occupied_tiles: occupied,
+            saturated_tiles: saturated,
+        }
+    }
+
+    /// `{"visible_gaussians": 1024, ...}`
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"visible_gaussians\": {}, \"culled_gaussians\": {}, \"duplicated_keys\": {}, \
+             \"max_splats_per_tile\": {}, \"mean_splats_per_tile\": {:.4}, \
+             \"occupied_tiles\": {}, \"saturated_tiles\": {}}}",
+            self.visible_gaussians,
+            self.culled_gaussians,
+            self.duplicated_keys,
+            self.max_splats_per_tile,
+            self.mean_splats_per_tile,
+            self.occupied_tiles,
+            self.saturated_tiles,
+        )
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>20}: {}", "visible gaussians", self.visible_gaussians)?;
+        writeln!(f, "{:>20}: {}", "culled gaussians", self.culled_gaussians)?;
+        writeln!(f, "{:>20}: {}", "duplicated keys", self.duplicated_keys)?;
+        writeln!(
+            f,
+            "{:>20}: {}",
+            "max splats per tile", self.max_splats_per_tile
+        )?;
+        writeln!(
+            f,
+            "{:>20}: {:.2}",
+            "mean splats per tile", self.mean_splats_per_tile
+        )?;
+        writeln!(f, "{:>20}: {}", "occupied tiles", self.occupied_tiles)?;
+        write!(f, "{:>20}: {}", "saturated tiles", self.saturated_tiles)
+    }
+}
@@ -0,0 +1,438 @@
+//! Out of core rendering of scenes larger than GPU memory.
+//!
+//! A chunk file holds the Gaussians of a scene split into spatially compact chunks behind an
+//! index of their bounds. A [`Streamer`] keeps the chunks nearest to the camera resident in a
+//! fixed set of renderer objects sized to a memory budget: it evicts the ones that fell out of
+//! range and asks for the ones to load, which the caller reads, e.g. with HTTP range requests,
+//! and hands back as they arrive.
+
+use std::ops::Range;
+
+use nalgebra::{Matrix4, Point3};
+
+use crate::{
+    ply::{self, GAUSSIAN_FLOATS},
+    renderer::BYTES_PER_GAUSSIAN,
+    Camera, Renderer,
+};
+
+const MAGIC: &[u8; 8] = b"gschk001";
+
+/// Bytes of an entry of the index.
+const ENTRY_SIZE: usize = 32;
+
+/// Part of a chunk file, see [`ChunkIndex`].
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    /// Bounds of the means.
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+    pub num_gaussian: u64,
+    /// Of its Gaussians in the chunk file.
+    pub offset: u64,
+}
+
+impl Chunk {
+    /// Bytes of its packed `Gaussian` structs in the chunk file, read with [`chunk_gaussians`].
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.num_gaussian * GAUSSIAN_FLOATS as u64 * 4
+    }
+
+    /// From `point` to the bounds, 0 inside.
+    pub fn distance(&self, point: &Point3<f32>) -> f32 {
+        nalgebra::distance(point, &point.sup(&self.min).inf(&self.max))
+    }
+}
+
+/// Index at the start of a chunk file, followed by the Gaussians of every chunk.
+#[derive(Clone, Debug)]
+pub struct ChunkIndex {
+    pub chunks: Vec<Chunk>,
+    /// Gaussians of the largest chunk.
+    pub chunk_size: u64,
+    /// The Gaussians have the 3D filter of Mip-Splatting.
+    pub mip_splatting: bool,
+}
+
+impl ChunkIndex {
+    /// Bytes at the start of a chunk file telling the size of the index, see
+    /// [`ChunkIndex::size`].
+    pub const PREFIX_SIZE: usize = 24;
+
+    /// Splits `num_gaussian` packed `Gaussian` structs, e.g. of a [`ply::PointCloud`], into
+    /// chunks of at most `chunk_size` at the median of their longest side, and returns the
+    /// chunk file.
+    pub fn build(
+        gaussians: &[f32],
+        num_gaussian: u64,
+        mip_splatting: bool,
+        chunk_size: u64,
+    ) -> Vec<u8> {
+        let means: Vec<Point3<f32>> = gaussians
+            .chunks_exact(GAUSSIAN_FLOATS)
+            .take(num_gaussian as usize)
+            .map(|gaussian| Point3::from_slice(&gaussian[ply::MEAN..ply::MEAN + 3]))
+            .collect();
+        let mut order: Vec<usize> = (0..means.len()).collect();
+        let mut sizes = Vec::new();
+        split(&means, &mut order, chunk_size.max(1) as usize, &mut sizes);
+
+        let mut offset = (Self::PREFIX_SIZE + sizes.len() * ENTRY_SIZE) as u64;
+        let mut items = order.as_slice();
+        let chunks: Vec<Chunk> = sizes
+            .iter()
+            .map(|&size| {
+                let (chunk, rest) = items.split_at(size);
+                items = rest;
+                let first = means[chunk[0]];
+                let (min, max) = chunk.iter().fold((first, first), |(min, max), &item| {
+                    (min.inf(&means[item]), max.sup(&means[item]))
+                });
+
+                let chunk = Chunk {
+                    min,
+                    max,
+                    num_gaussian: size as u64,
+                    offset,
+                };
+                offset = chunk.range().end;
+                chunk
+            })
+            .collect();
+
+        let mut bytes = Vec::with_capacity(offset as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(mip_splatting as u32).to_le_bytes());
+        bytes.extend_from_slice(&(sizes.iter().max().copied().unwrap_or(0) as u64).to_le_bytes());
+        for chunk in &chunks {
+            for value in chunk.min.iter().chain(chunk.max.iter()) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&chunk.num_gaussian.to_le_bytes());
+        }
+        for &item in &order {
+            let gaussian = &gaussians[item * GAUSSIAN_FLOATS..(item + 1) * GAUSSIAN_FLOATS];
+            for value in gaussian {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Bytes of the index from the first [`ChunkIndex::PREFIX_SIZE`] of a chunk file.
+    pub fn size(prefix: &[u8]) -> Result<usize, String> {
+        if prefix.get(..MAGIC.len()) != Some(MAGIC) {
+            return Err("not a Gaussian chunk file".to_string());
+        }
+        let num_chunk = prefix
+            .get(8..12)
+            .ok_or("truncated chunk file header")?
+            .try_into()
+            .unwrap();
+
+        let num_chunk = u32::from_le_bytes(num_chunk) as usize;
+        num_chunk
+            .checked_mul(ENTRY_SIZE)
+            .and_then(|size| size.checked_add(Self::PREFIX_SIZE))
+            .ok_or_else(|| format!("chunk index of {num_chunk} chunks"))
+    }
+
+    /// Reads the index from the start of a chunk file, the Gaussians may be missing. Fails
+    /// when a chunk is larger than [`ChunkIndex::chunk_size`].
+    pub fn load(bytes: &[u8]) -> Result<Self, String> {
+        let size = Self::size(bytes)?;
+        let bytes = bytes.get(..size).ok_or("truncated chunk index")?;
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let f32_at = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let point_at = |at: usize| Point3::new(f32_at(at), f32_at(at + 4), f32_at(at + 8));
+
+        let chunk_size = u64_at(16);
+        // a chunk is one object of the renderer, whose Gaussians are counted in u32
+        if chunk_size > u32::MAX as u64 {
+            return Err(format!("chunks of {chunk_size} Gaussians"));
+        }
+
+        let mut offset = size as u64;
+        let chunks = (Self::PREFIX_SIZE..size)
+            .step_by(ENTRY_SIZE)
+            .map(|at| {
+                let num_gaussian = u64_at(at + 24);
+                if num_gaussian > chunk_size {
+                    return Err(format!(
+                        "chunk of {num_gaussian} Gaussians in chunks of at most {chunk_size}"
+                    ));
+                }
+
+                let chunk = Chunk {
+                    min: point_at(at),
+                    max: point_at(at + 12),
+                    num_gaussian,
+                    offset,
+                };
+                offset = chunk.range().end;
+                Ok(chunk)
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            chunks,
+            mip_splatting: u32_at(12) != 0,
+            chunk_size,
+        })
+    }
+}
+
+/// Packed `Gaussian` structs from the bytes of [`Chunk::range`].
+pub fn chunk_gaussians(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+/// Orders `items` into consecutive chunks of at most `chunk_size` and appends their sizes.
+fn split(means: &[Point3<f32>], items: &mut [usize], chunk_size: usize, sizes: &mut Vec<usize>) {
+    if items.len() <= chunk_size {
+        if !items.is_empty() {
+            sizes.push(items.len());
+        }
+        return;
+    }
+
+    let first = means[items[0]];
+    let (min, max) = items.iter().fold((first, first), |(min, max), &item| {
+        (min.inf(&means[item]), max.sup(&means[item]))
+    });
+    let axis = (max - min).imax();
+
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |&a, &b| means[a][axis].total_cmp(&means[b][axis]));
+    let (near, far) = items.split_at_mut(middle);
+    split(means, near, chunk_size, sizes);
+    split(means, far, chunk_size, sizes);
+}
+
+/// Keeps the chunks nearest to the camera in renderer objects reserved for them.
+pub struct Streamer {
+    index: ChunkIndex,
+    /// renderer objects holding a chunk each
+    objects: Range<usize>,
+    /// chunk in every object
+    resident: Vec<Option<usize>>,
+    /// asked for by `update` and not uploaded yet
+    pending: Vec<usize>,
+}
+
+impl Streamer {
+    /// Reserves as many objects of [`ChunkIndex::chunk_size`] Gaussians in `renderer` as
+    /// `budget` bytes of GPU memory hold, at least one. The sort buffers, textures and other
+    /// objects come on top, except for the copy of the other objects made while the buffers
+    /// grow, which is taken out of `budget` so the peak stays within it. Create the renderer
+    /// without Gaussians for a streamed scene alone, which copies nothing.
+    pub fn new(
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: ChunkIndex,
+        budget: u64,
+    ) -> Self {
+        let chunk_bytes = index.chunk_size.max(1) * BYTES_PER_GAUSSIAN;
+        let budget = budget.saturating_sub(renderer.scene_size());
+        let count = (budget / chunk_bytes).clamp(1, index.chunks.len().max(1) as u64) as usize;
+        let objects = renderer.reserve_objects(device, queue, count, index.chunk_size);
+
+        Self {
+            index,
+            objects,
+            resident: vec![None; count],
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn index(&self) -> &ChunkIndex {
+        &self.index
+    }
+
+    /// Renderer objects the chunks are streamed into.
+    pub fn objects(&self) -> Range<usize> {
+        self.objects.clone()
+    }
+
+    /// Chunks uploaded into the renderer.
+    pub fn resident(&self) -> impl Iterator<Item = usize> + '_ {
+        self.resident.iter().flatten().copied()
+    }
+
+    /// Evicts the chunks no longer among the nearest to `camera` that fit, and returns the
+    /// ones to load, nearest first. Each should be passed to [`Streamer::upload`] once read.
+    /// A chunk asked for is not asked for again until then, or until it is given up with
+    /// [`Streamer::cancel`], e.g. after a failed read.
+    ///
+    /// The chunks are placed by the transform of the first of [`Streamer::objects`], a chunk
+    /// moves between objects so all of them should get the same one with
+    /// [`Renderer::set_object`].
+    pub fn update(
+        &mut self,
+        renderer: &Renderer,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+    ) -> Vec<usize> {
+        // the chunks are in object space
+        let object = renderer
+            .object(self.objects.start)
+            .expect("an object of the streamer");
+        let to_object = (renderer.transform() * &object.transform)
+            .matrix()
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        let position = to_object.transform_point(&camera.position());
+
+        let mut wanted: Vec<usize> = (0..self.index.chunks.len()).collect();
+        let distance = |chunk: &usize| self.index.chunks[*chunk].distance(&position);
+        wanted.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        wanted.truncate(self.resident.len());
+
+        for (object, resident) in self.objects.clone().zip(&mut self.resident) {
+            if resident.is_some_and(|chunk| !wanted.contains(&chunk)) {
+                renderer
+                    .write_object(queue, object, &[], 0)
+                    .expect("an object of the streamer");
+                *resident = None;
+            }
+        }
+        self.pending.retain(|chunk| wanted.contains(chunk));
+
+        let load: Vec<usize> = wanted
+            .into_iter()
+            .filter(|chunk| !self.resident.contains(&Some(*chunk)) && !self.pending.contains(chunk))
+            .collect();
+        self.pending.extend(&load);
+
+        load
+    }
+
+    /// Gives up loading `chunk` asked for by [`Streamer::update`], the next update asks for it
+    /// again if still wanted.
+    pub fn cancel(&mut self, chunk: usize) {
+        self.pending.retain(|&pending| pending != chunk);
+    }
+
+    /// Uploads the Gaussians of `chunk` asked for by [`Streamer::update`], drawn from the next
+    /// frame on. Returns `false` and drops them when the chunk is no longer wanted, or when
+    /// they are not the chunk's Gaussians, e.g. of a truncated read, which the next update
+    /// asks for again.
+    pub fn upload(
+        &mut self,
+        renderer: &Renderer,
+        queue: &wgpu::Queue,
+        chunk: usize,
+        gaussians: &[f32],
+    ) -> bool {
+        let Some(pending) = self.pending.iter().position(|&pending| pending == chunk) else {
+            return false;
+        };
+        self.pending.swap_remove(pending);
+
+        let num_gaussian = self.index.chunks[chunk].num_gaussian;
+        if gaussians.len() as u64 != num_gaussian * GAUSSIAN_FLOATS as u64 {
+            return false;
+        }
+
+        // every wanted chunk has an object once the others are evicted
+        let free = self
+            .resident
+            .iter()
+            .position(Option::is_none)
+            .expect("a free object for every pending chunk");
+        let written = renderer
+            .write_object(queue, self.objects.start + free, gaussians, num_gaussian)
+            .is_ok();
+        if written {
+            self.resident[free] = Some(chunk);
+        }
+
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gaussians with the means `means`.
+    fn gaussians(means: &[[f32; 3]]) -> Vec<f32> {
+        means
+            .iter()
+            .flat_map(|mean| {
+                let mut gaussian = [0.0; GAUSSIAN_FLOATS];
+                gaussian[ply::MEAN..ply::MEAN + 3].copy_from_slice(mean);
+                gaussian
+            })
+            .collect()
+    }
+
+    fn line(n: usize) -> Vec<[f32; 3]> {
+        (0..n).map(|i| [i as f32, (i % 3) as f32, 0.0]).collect()
+    }
+
+    #[test]
+    fn split_bounds_chunk_size() {
+        let means: Vec<Point3<f32>> = line(100).iter().map(|&mean| mean.into()).collect();
+        let mut order: Vec<usize> = (0..means.len()).collect();
+        let mut sizes = Vec::new();
+        split(&means, &mut order, 7, &mut sizes);
+
+        assert!(sizes.iter().all(|&size| 0 < size && size <= 7));
+        assert_eq!(sizes.iter().sum::<usize>(), 100);
+        order.sort_unstable();
+        assert!(order.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn build_load_round_trip() {
+        let means = line(100);
+        let gaussians = gaussians(&means);
+        let bytes = ChunkIndex::build(&gaussians, 100, true, 16);
+
+        let size = ChunkIndex::size(&bytes[..ChunkIndex::PREFIX_SIZE]).unwrap();
+        let index = ChunkIndex::load(&bytes[..size]).unwrap();
+        assert!(index.mip_splatting);
+        assert_eq!(
+            size,
+            ChunkIndex::PREFIX_SIZE + index.chunks.len() * ENTRY_SIZE
+        );
+
+        // contiguous from the end of the index to the end of the file
+        let mut offset = size as u64;
+        let mut total = 0;
+        for chunk in &index.chunks {
+            assert!(0 < chunk.num_gaussian && chunk.num_gaussian <= index.chunk_size);
+            assert!(index.chunk_size <= 16);
+            assert_eq!(chunk.range().start, offset);
+            offset = chunk.range().end;
+            total += chunk.num_gaussian;
+
+            let range = chunk.range();
+            let chunk_gaussians = chunk_gaussians(&bytes[range.start as usize..range.end as usize]);
+            for gaussian in chunk_gaussians.chunks_exact(GAUSSIAN_FLOATS) {
+                let mean = Point3::from_slice(&gaussian[ply::MEAN..ply::MEAN + 3]);
+                assert_eq!(chunk.distance(&mean), 0.0);
+            }
+        }
+        assert_eq!(offset, bytes.len() as u64);
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn load_rejects_oversized_chunks() {
+        let mut bytes = ChunkIndex::build(&gaussians(&line(10)), 10, false, 4);
+        assert!(ChunkIndex::load(&bytes[..ChunkIndex::PREFIX_SIZE]).is_err());
+
+        bytes[16..24].copy_from_slice(&1_u64.to_le_bytes());
+        assert!(ChunkIndex::load(&bytes).is_err());
+        assert!(ChunkIndex::size(b"gschk000").is_err());
+    }
+}
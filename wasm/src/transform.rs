@@ -0,0 +1,44 @@
+use std::ops::Mul;
+
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
+
+/// Placement in world space, applied to the Gaussians on the GPU: scaled uniformly, rotated,
+/// then translated.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Transform {
+    /// Model to world.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_scaling(self.scale)
+    }
+}
+
+/// `a * b` applies `b` first, the scale stays uniform.
+impl Mul for &Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Self) -> Transform {
+        Transform {
+            translation: self.translation + self.rotation * (rhs.translation * self.scale),
+            rotation: self.rotation * rhs.rotation,
+            scale: self.scale * rhs.scale,
+        }
+    }
+}
@@ -0,0 +1,640 @@
+//! Renderer kept alive between frames by the web app, drawing WebXR views into a canvas.
+
+use nalgebra::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector2, Vector3};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{HtmlCanvasElement, XrFrame, XrReferenceSpace, XrView};
+
+use crate::{
+    chunk_gaussians, ply, Camera, Chunk, ChunkIndex, Crop, CropShape, FrameStats, LodCut, LodTree,
+    Measurement, Pick, RenderSettings, RenderTarget, Renderer, SceneObject, SelectMode, Selector,
+    Streamer, Transform, View, MAX_VIEWS,
+};
+
+/// Yellow lines and markers of the measurements.
+const MEASUREMENT_COLOR: [f32; 4] = [1.0, 0.85, 0.1, 1.0];
+
+#[wasm_bindgen]
+pub struct WebRenderer {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: Renderer,
+    /// XR reference space to scene transform
+    xr_origin: Matrix4<f32>,
+    /// drawn over every view
+    measurements: Vec<Measurement>,
+    streamer: Option<Streamer>,
+    /// level of detail objects, refined for the first view of every frame
+    lod_cuts: Vec<LodCut>,
+}
+
+#[wasm_bindgen]
+impl WebRenderer {
+    /// Uploads the scene and renders `width` x `height` views side by side into `canvas`,
+    /// which is resized to hold [`MAX_VIEWS`] of them.
+    pub async fn create(
+        canvas: HtmlCanvasElement,
+        gaussians: &[f32],
+        num_gaussian: u64,
+        width: u32,
+        height: u32,
+        settings: &RenderSettings,
+    ) -> Result<WebRenderer, JsValue> {
+        let canvas_width = width * MAX_VIEWS as u32;
+        canvas.set_width(canvas_width);
+        canvas.set_height(height);
+
+        let instance = wgpu::Instance::default();
+        let surface = instance
+            .create_surface(wgpu::SurfaceTarget::Canvas(canvas))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or("no suitable adapter")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: if settings.profile {
+                        adapter.features() & wgpu::Features::TIMESTAMP_QUERY
+                    } else {
+                        wgpu::Features::empty()
+                    },
+                    required_limits: Renderer::required_limits(&adapter),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let max_size = device.limits().max_texture_dimension_2d;
+        if canvas_width > max_size || height > max_size {
+            return Err(JsValue::from_str(&format!(
+                "{MAX_VIEWS} views of {width}x{height} need a {canvas_width}x{height} canvas, \
+                 the device supports at most {max_size} pixels per side"
+            )));
+        }
+
+        let config = surface
+            .get_default_config(&adapter, canvas_width, height)
+            .ok_or("canvas is not supported by the adapter")?;
+        surface.configure(&device, &config);
+
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            config.format,
+            gaussians,
+            num_gaussian,
+            width,
+            height,
+            settings.clone(),
+        )
+        .await;
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            renderer,
+            // COLMAP scenes have y down, a half turn around x puts them upright
+            xr_origin: Matrix4::from_diagonal(&nalgebra::Vector4::new(1.0, -1.0, -1.0, 1.0)),
+            measurements: Vec::new(),
+            streamer: None,
+            lod_cuts: Vec::new(),
+        })
+    }
+
+    /// Transform from the XR reference space to the scene, 16 floats in column major order.
+    pub fn set_xr_origin(&mut self, matrix: &[f32]) {
+        self.xr_origin = Matrix4::from_column_slice(matrix);
+    }
+
+    /// Places the scene in world space: scaled by `scale`, rotated by the quaternion
+    /// `rotation` in x, y, z, w order, then moved by `translation`.
+    pub fn set_transform(
+        &mut self,
+        translation: &[f32],
+        rotation: &[f32],
+        scale: f32,
+    ) -> Result<(), JsValue> {
+        self.renderer
+            .set_transform(&self.queue, transform(translation, rotation, scale)?);
+        Ok(())
+    }
+
+    /// Uploads another splat asset into the scene, returns its index. The scene loaded by
+    /// [`WebRenderer::create`] is object 0. Fails when `gaussians` holds another number of
+    /// them.
+    pub fn add_object(&mut self, gaussians: &[f32], num_gaussian: u64) -> Result<usize, JsValue> {
+        self.renderer
+            .add_object(&self.device, &self.queue, gaussians, num_gaussian)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Adds the hierarchy of a `.lod` file built by the `gs` CLI, drawn coarser where its
+    /// nodes project to at most `RenderSettings.lod_threshold` pixels and only as far as
+    /// `budget` megabytes of GPU memory hold. Returns its index.
+    pub fn add_lod_object(&mut self, bytes: &[u8], budget: u32) -> Result<usize, JsValue> {
+        let tree = LodTree::load(bytes).map_err(|e| JsValue::from_str(&e))?;
+        let lod_cut = LodCut::new(
+            &mut self.renderer,
+            &self.device,
+            &self.queue,
+            tree,
+            (budget as u64) << 20,
+        );
+        let index = lod_cut.object();
+        self.lod_cuts.push(lod_cut);
+        Ok(index)
+    }
+
+    /// Streams the chunks of a `.chunks` file built by the `gs` CLI into `budget` megabytes of
+    /// GPU memory, `index` is its first [`ChunkIndex::size`] bytes. Create the renderer
+    /// without Gaussians for a streamed scene alone.
+    pub fn start_streaming(&mut self, index: &[u8], budget: u32) -> Result<(), JsValue> {
+        let index = ChunkIndex::load(index).map_err(|e| JsValue::from_str(&e))?;
+        self.streamer = Some(Streamer::new(
+            &mut self.renderer,
+            &self.device,
+            &self.queue,
+            index,
+            (budget as u64) << 20,
+        ));
+        Ok(())
+    }
+
+    /// Evicts the chunks out of range of a camera like the one of [`WebRenderer::select_rect`]
+    /// and returns the ones to fetch, nearest first, for [`WebRenderer::upload_chunk`].
+    pub fn update_streaming(&mut self, view: &[f32], focal: &[f32], principal: &[f32]) -> Vec<u32> {
+        let camera = self.camera(view, focal, principal);
+        let Some(streamer) = &mut self.streamer else {
+            return Vec::new();
+        };
+
+        streamer
+            .update(&self.renderer, &self.queue, &camera)
+            .into_iter()
+            .map(|chunk| chunk as u32)
+            .collect()
+    }
+
+    /// Start and end of the bytes of `chunk` in the chunk file, e.g. for an HTTP range request.
+    /// Empty for a chunk that does not exist.
+    pub fn chunk_range(&self, chunk: u32) -> Vec<f64> {
+        let range = self
+            .streamer
+            .as_ref()
+            .and_then(|streamer| streamer.index().chunks.get(chunk as usize))
+            .map_or(0..0, Chunk::range);
+        vec![range.start as f64, range.end as f64]
+    }
+
+    /// Uploads the fetched bytes of `chunk`, `false` when it is no longer wanted or the bytes
+    /// are not the whole chunk.
+    pub fn upload_chunk(&mut self, chunk: u32, bytes: &[u8]) -> bool {
+        let Some(streamer) = &mut self.streamer else {
+            return false;
+        };
+
+        streamer.upload(
+            &self.renderer,
+            &self.queue,
+            chunk as usize,
+            &chunk_gaussians(bytes),
+        )
+    }
+
+    /// Gives up loading `chunk`, e.g. after a failed fetch, the next
+    /// [`WebRenderer::update_streaming`] asks for it again if still wanted.
+    pub fn cancel_chunk(&mut self, chunk: u32) {
+        if let Some(streamer) = &mut self.streamer {
+            streamer.cancel(chunk as usize);
+        }
+    }
+
+    /// Places the Gaussians of object `index` once more, e.g. the same tree all over a scene,
+    /// without uploading them again. Returns the index of the new object, fails for an unknown
+    /// object.
+    pub fn add_instance(&mut self, index: usize) -> Result<usize, JsValue> {
+        self.renderer
+            .add_instance(&self.device, index)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Places the object `index` in the scene like [`WebRenderer::set_transform`], hides it
+    /// unless `visible` and multiplies the opacity of its Gaussians by `opacity`. Fails for an
+    /// unknown object.
+    pub fn set_object(
+        &mut self,
+        index: usize,
+        translation: &[f32],
+        rotation: &[f32],
+        scale: f32,
+        visible: bool,
+        opacity: f32,
+    ) -> Result<(), JsValue> {
+        self.renderer
+            .set_object(
+                &self.queue,
+                index,
+                SceneObject {
+                    transform: transform(translation, rotation, scale)?,
+                    visible,
+                    opacity,
+                },
+            )
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Discards the Gaussians outside of the box between the corners `min` and `max`, or
+    /// inside of it when `invert`.
+    pub fn add_crop_box(&mut self, min: &[f32], max: &[f32], invert: bool) -> Result<(), JsValue> {
+        self.add_crop(
+            CropShape::Box {
+                min: point(min)?,
+                max: point(max)?,
+            },
+            invert,
+        );
+        Ok(())
+    }
+
+    /// Like [`WebRenderer::add_crop_box`] for a box rotated by the quaternion `rotation` in
+    /// x, y, z, w order around its `center`.
+    pub fn add_crop_oriented_box(
+        &mut self,
+        center: &[f32],
+        half_extent: &[f32],
+        rotation: &[f32],
+        invert: bool,
+    ) -> Result<(), JsValue> {
+        self.add_crop(
+            CropShape::OrientedBox {
+                center: point(center)?,
+                half_extent: vector(half_extent)?,
+                rotation: quaternion(rotation)?,
+            },
+            invert,
+        );
+        Ok(())
+    }
+
+    pub fn add_crop_sphere(
+        &mut self,
+        center: &[f32],
+        radius: f32,
+        invert: bool,
+    ) -> Result<(), JsValue> {
+        self.add_crop(
+            CropShape::Sphere {
+                center: point(center)?,
+                radius,
+            },
+            invert,
+        );
+        Ok(())
+    }
+
+    /// Discards the Gaussians where `normal · p + distance < 0`.
+    pub fn add_crop_plane(&mut self, normal: &[f32], distance: f32) -> Result<(), JsValue> {
+        self.add_crop(
+            CropShape::Plane {
+                normal: vector(normal)?,
+                distance,
+            },
+            false,
+        );
+        Ok(())
+    }
+
+    pub fn clear_crops(&mut self) {
+        self.renderer.set_crops(&self.device, Vec::new());
+    }
+
+    /// Selects the Gaussians inside the rectangle between the pixels `min` and `max` of a view
+    /// seen through the world to view matrix `view`, 16 floats in column major order, with
+    /// the focal lengths `focal` and the principal point `principal` in pixels.
+    pub fn select_rect(
+        &self,
+        view: &[f32],
+        focal: &[f32],
+        principal: &[f32],
+        min: &[f32],
+        max: &[f32],
+        mode: SelectMode,
+    ) {
+        self.select(
+            &Selector::Rect {
+                camera: self.camera(view, focal, principal),
+                min: Vector2::from_column_slice(min),
+                max: Vector2::from_column_slice(max),
+            },
+            mode,
+        );
+    }
+
+    /// Like [`WebRenderer::select_rect`] for the polygon with the pixels `points`, x and y
+    /// interleaved.
+    pub fn select_lasso(
+        &self,
+        view: &[f32],
+        focal: &[f32],
+        principal: &[f32],
+        points: &[f32],
+        mode: SelectMode,
+    ) {
+        self.select(
+            &Selector::Lasso {
+                camera: self.camera(view, focal, principal),
+                points: points
+                    .chunks_exact(2)
+                    .map(Vector2::from_column_slice)
+                    .collect(),
+            },
+            mode,
+        );
+    }
+
+    pub fn select_box(&self, min: &[f32], max: &[f32], mode: SelectMode) -> Result<(), JsValue> {
+        self.select(
+            &Selector::Crop(Crop::new(CropShape::Box {
+                min: point(min)?,
+                max: point(max)?,
+            })),
+            mode,
+        );
+        Ok(())
+    }
+
+    pub fn select_sphere(
+        &self,
+        center: &[f32],
+        radius: f32,
+        mode: SelectMode,
+    ) -> Result<(), JsValue> {
+        self.select(
+            &Selector::Crop(Crop::new(CropShape::Sphere {
+                center: point(center)?,
+                radius,
+            })),
+            mode,
+        );
+        Ok(())
+    }
+
+    pub fn select_opacity(&self, min: f32, max: f32, mode: SelectMode) {
+        self.select(&Selector::Opacity(min..=max), mode);
+    }
+
+    /// Selects by the largest axis of the Gaussians in world space.
+    pub fn select_scale(&self, min: f32, max: f32, mode: SelectMode) {
+        self.select(&Selector::Scale(min..=max), mode);
+    }
+
+    pub fn clear_selection(&self) {
+        self.renderer.clear_selection(&self.device, &self.queue);
+    }
+
+    pub fn hide_selection(&self) {
+        self.renderer.hide_selection(&self.device, &self.queue);
+    }
+
+    pub fn show_hidden(&self) {
+        self.renderer.show_hidden(&self.device, &self.queue);
+    }
+
+    pub fn delete_selection(&self) {
+        self.renderer.delete_selection(&self.device, &self.queue);
+    }
+
+    /// Paints the selection in the flat color `r`, `g`, `b` in [0, 1].
+    pub fn recolor_selection(&self, r: f32, g: f32, b: f32) {
+        self.renderer
+            .recolor_selection(&self.device, &self.queue, Vector3::new(r, g, b));
+    }
+
+    /// Dominant Gaussian and expected surface under the pixel `x`, `y` of a view like the
+    /// one of [`WebRenderer::select_rect`], `undefined` when nothing is seen there.
+    pub async fn pick(
+        &self,
+        view: &[f32],
+        focal: &[f32],
+        principal: &[f32],
+        x: u32,
+        y: u32,
+    ) -> Option<Pick> {
+        self.renderer
+            .pick(
+                &self.device,
+                &self.queue,
+                &self.camera(view, focal, principal),
+                Vector2::new(x, y),
+            )
+            .await
+    }
+
+    /// Draws lines through the world space `points`, x, y and z interleaved, over the views,
+    /// back to the first point when `closed`.
+    pub fn add_measurement(&mut self, points: &[f32], closed: bool) {
+        self.measurements.push(measurement(points, closed));
+    }
+
+    pub fn clear_measurements(&mut self) {
+        self.measurements.clear();
+    }
+
+    /// Binary PLY of the edited Gaussians of the object `index`, fails for an unknown object.
+    pub async fn save_ply(
+        &self,
+        index: usize,
+        mip_splatting: bool,
+    ) -> Result<js_sys::Uint8Array, JsValue> {
+        let gaussians = self
+            .renderer
+            .read_gaussians(&self.device, &self.queue, index)
+            .await
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(ply::save(&gaussians, mip_splatting).as_slice().into())
+    }
+
+    /// Width of a view, the canvas holds [`MAX_VIEWS`] of them side by side.
+    pub fn view_width(&self) -> u32 {
+        self.renderer.width()
+    }
+
+    /// Statistics of the newest frame read back so far, `None` until one is and unless the
+    /// renderer was created with `RenderSettings::stats`. Never waits for the GPU, call it
+    /// after every frame.
+    pub fn read_stats(&self) -> Option<FrameStats> {
+        self.renderer.read_stats(&self.device)
+    }
+
+    /// Renders the views of the viewer pose of `frame` in `space` side by side into the canvas,
+    /// view `i` starting at `i * width`. `false` when the pose is not tracked.
+    pub fn render_xr_frame(&mut self, frame: &XrFrame, space: &XrReferenceSpace) -> bool {
+        let Some(pose) = frame.get_viewer_pose(space) else {
+            return false;
+        };
+
+        let cameras: Vec<Camera> = pose
+            .views()
+            .iter()
+            .take(MAX_VIEWS)
+            .map(|view| {
+                let view: XrView = view.unchecked_into();
+                let transform = Matrix4::from_column_slice(&view.transform().matrix());
+                let projection = Matrix4::from_column_slice(&view.projection_matrix());
+
+                Camera::from_xr_view(
+                    &(self.xr_origin * transform),
+                    &projection,
+                    self.renderer.width(),
+                    self.renderer.height(),
+                )
+            })
+            .collect();
+
+        self.render_cameras(&cameras);
+
+        true
+    }
+}
+
+impl WebRenderer {
+    fn select(&self, selector: &Selector, mode: SelectMode) {
+        self.renderer
+            .select(&self.device, &self.queue, selector, mode);
+    }
+
+    fn camera(&self, view: &[f32], focal: &[f32], principal: &[f32]) -> Camera {
+        Camera::new(
+            Matrix4::from_column_slice(view),
+            Vector2::from_column_slice(focal),
+            Vector2::from_column_slice(principal),
+        )
+    }
+
+    fn add_crop(&mut self, shape: CropShape, invert: bool) {
+        let mut crops = self.renderer.crops().to_vec();
+        crops.push(Crop { shape, invert });
+        self.renderer.set_crops(&self.device, crops);
+    }
+
+    fn render_cameras(&mut self, cameras: &[Camera]) {
+        if let Some(camera) = cameras.first() {
+            for lod_cut in &mut self.lod_cuts {
+                lod_cut.update(&self.renderer, &self.queue, camera);
+            }
+        }
+
+        let frame = self
+            .surface
+            .get_current_texture()
+            .expect("Failed to acquire next swap chain texture");
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("xr encoder"),
+            });
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        let target = RenderTarget::new(&view);
+        let views: Vec<View> = cameras
+            .iter()
+            .enumerate()
+            .map(|(i, camera)| View {
+                camera,
+                target,
+                origin: [i as u32 * self.renderer.width(), 0],
+            })
+            .collect();
+
+        self.renderer
+            .render_views(&self.device, &self.queue, &mut encoder, &views)
+            .expect("at most MAX_VIEWS cameras");
+        for view in &views {
+            self.renderer.render_measurements(
+                &self.device,
+                &mut encoder,
+                view,
+                &self.measurements,
+                MEASUREMENT_COLOR,
+            );
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+}
+
+/// Length of the path through `points`, x, y and z interleaved, back to the first point when
+/// `closed`.
+#[wasm_bindgen]
+pub fn measure_length(points: &[f32], closed: bool) -> f32 {
+    measurement(points, closed).length()
+}
+
+/// Area of the polygon through `points`, x, y and z interleaved.
+#[wasm_bindgen]
+pub fn measure_area(points: &[f32]) -> f32 {
+    measurement(points, true).area()
+}
+
+fn measurement(points: &[f32], closed: bool) -> Measurement {
+    Measurement {
+        points: points.chunks_exact(3).map(Point3::from_slice).collect(),
+        closed,
+    }
+}
+
+fn transform(translation: &[f32], rotation: &[f32], scale: f32) -> Result<Transform, JsValue> {
+    Ok(Transform {
+        translation: vector(translation)?,
+        rotation: quaternion(rotation)?,
+        scale,
+    })
+}
+
+/// `values` in x, y, z order.
+fn vector(values: &[f32]) -> Result<Vector3<f32>, JsValue> {
+    let [x, y, z] = values
+        .try_into()
+        .map_err(|_| JsValue::from_str(&format!("{} floats are not a 3D vector", values.len())))?;
+    Ok(Vector3::new(x, y, z))
+}
+
+fn point(values: &[f32]) -> Result<Point3<f32>, JsValue> {
+    vector(values).map(Point3::from)
+}
+
+/// `rotation` in x, y, z, w order.
+fn quaternion(rotation: &[f32]) -> Result<UnitQuaternion<f32>, JsValue> {
+    let [x, y, z, w] = rotation.try_into().map_err(|_| {
+        JsValue::from_str(&format!("{} floats are not a quaternion", rotation.len()))
+    })?;
+    Ok(UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)))
+}